@@ -1,4 +1,5 @@
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
 use crate::{vm::bool_to_int, Pair};
 
@@ -14,16 +15,43 @@ pub enum FN /*monadic operator*/ {
     Max,
     Min,
     Eq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Ne,
     Amp,
     Bang,
     Rho,
+    At,
+    GradeUp,
+    GradeDown,
+    SortUp,
+    SortDown,
+    Reverse,
+    Transpose,
+    Take,
+    Drop,
+    Cat,
+    CatFirst,
+    IndexOf,
+    Member,
+    Union,
+    Intersect,
+    Without,
+    Group,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
 pub enum CN /*combinators*/ {
     Fold,
-    ScanL,
+    Scan,
+    EachLeft,
+    Outer,
     Each,
+    Converge,
+    Power,
+    Key,
 }
 
 impl FN {
@@ -36,9 +64,31 @@ impl FN {
             FN::Max => "¯",
             FN::Min => "_",
             FN::Eq => "=",
+            FN::Lt => "<",
+            FN::Gt => ">",
+            FN::Le => "≤",
+            FN::Ge => "≥",
+            FN::Ne => "≠",
             FN::Amp => "&",
             FN::Bang => "!",
             FN::Rho => "ρ",
+            FN::At => "@",
+            FN::GradeUp => "⍋",
+            FN::GradeDown => "⍒",
+            FN::SortUp => "∧",
+            FN::SortDown => "∨",
+            FN::Reverse => "⌽",
+            FN::Transpose => "⍉",
+            FN::Take => "↑",
+            FN::Drop => "↓",
+            FN::Cat => ",",
+            FN::CatFirst => "⍪",
+            FN::IndexOf => "⍳",
+            FN::Member => "∊",
+            FN::Union => "∪",
+            FN::Intersect => "∩",
+            FN::Without => "~",
+            FN::Group => "⊔",
         }
     }
 
@@ -51,9 +101,31 @@ impl FN {
             "¯" => FN::Max,
             "_" => FN::Min,
             "=" => FN::Eq,
+            "<" => FN::Lt,
+            ">" => FN::Gt,
+            "≤" => FN::Le,
+            "≥" => FN::Ge,
+            "≠" => FN::Ne,
             "&" => FN::Amp,
             "!" => FN::Bang,
             "ρ" => FN::Rho,
+            "@" => FN::At,
+            "⍋" => FN::GradeUp,
+            "⍒" => FN::GradeDown,
+            "∧" => FN::SortUp,
+            "∨" => FN::SortDown,
+            "⌽" => FN::Reverse,
+            "⍉" => FN::Transpose,
+            "↑" => FN::Take,
+            "↓" => FN::Drop,
+            "," => FN::Cat,
+            "⍪" => FN::CatFirst,
+            "⍳" => FN::IndexOf,
+            "∊" => FN::Member,
+            "∪" => FN::Union,
+            "∩" => FN::Intersect,
+            "~" => FN::Without,
+            "⊔" => FN::Group,
             _ => unreachable!("Unknown FN: {}", s),
         }
     }
@@ -69,15 +141,25 @@ impl CN {
     pub fn as_str(&self) -> &'static str {
         match self {
             CN::Fold => "/",
-            CN::ScanL => "\\",
+            CN::Scan => "\\",
+            CN::EachLeft => "⊣",
+            CN::Outer => "∘",
             CN::Each => "ǁ",
+            CN::Converge => "⍟",
+            CN::Power => "⍣",
+            CN::Key => "⌸",
         }
     }
     pub fn from_string(s: &str) -> CN {
         match s {
             "/" => CN::Fold,
-            "\\" => CN::ScanL,
+            "\\" => CN::Scan,
+            "⊣" => CN::EachLeft,
+            "∘" => CN::Outer,
             "ǁ" => CN::Each,
+            "⍟" => CN::Converge,
+            "⍣" => CN::Power,
+            "⌸" => CN::Key,
             _ => unreachable!("Unknown CN: {}", s),
         }
     }
@@ -105,6 +187,22 @@ impl PartialEq for NN {
     }
 }
 
+// Consistent with PartialEq: bools hash as the ints they equal, and both
+// float zeros hash alike
+impl Hash for NN {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match &self.n {
+            E::INT(i) => (0u8, *i).hash(state),
+            E::BOOL(b) => (0u8, bool_to_int(*b)).hash(state),
+            E::FT(f) => (1u8, if *f == 0.0 { 0 } else { f.to_bits() }).hash(state),
+            E::ST(s) => (2u8, s).hash(state),
+            E::LIST(l) => (3u8, l).hash(state),
+            E::VAL(s) => (4u8, s).hash(state),
+            other => std::mem::discriminant(other).hash(state),
+        }
+    }
+}
+
 impl NN {
     pub fn new(p: Pair, n: E) -> NN {
         let s = p.as_span();
@@ -176,6 +274,9 @@ pub enum E {
     // Monadic-override marker (op followed by :)
     MOP(FN),
 
+    // Inner product (e.g. +.×): reducing fn, combining fn
+    INNER(FN, FN),
+
     // List / array
     LIST(Vec<NN>),
 
@@ -195,6 +296,19 @@ pub enum E {
     // Do-block: (↻ expr1 expr2 ... exprN) — evaluate all, return last
     DOBLOCK(Vec<NN>),
 
+    // While loop: (⟳ test body...) — repeat body while test holds
+    WHILE {
+        test: Box<NN>,
+        body: Vec<NN>,
+    },
+
+    // Conditional: (? test1 then1 test2 then2 ... else)
+    // Only the chosen branch is evaluated
+    COND {
+        arms: Vec<(NN, NN)>,
+        otherwise: Box<NN>,
+    },
+
     // Assignment: (: name expr)
     ASEXP {
         name: String,
@@ -205,9 +319,10 @@ pub enum E {
     // Self-contained: carries its own bytecode and constants so it works
     // across REPL compilation units.
     UFNV {
-        nparams: usize,    // number of parameters
-        body_op: Vec<u8>,  // bytecode for the function body (STORE params + body + END)
-        body_var: Vec<NN>, // constant pool entries the body references
+        nparams: usize,         // number of parameters
+        body_op: Vec<u8>,       // bytecode for the function body (STORE params + body + END)
+        body_var: Vec<NN>,      // constant pool entries the body references
+        env: Vec<(String, NN)>, // bindings captured from the frame that created it
     },
 }
 
@@ -224,6 +339,7 @@ impl fmt::Display for E {
             E::CN(co) => write!(f, "{}", co),
             E::DCO { o, co } | E::MCO { o, co } => write!(f, "{}{}", o, co),
             E::MOP(o) => write!(f, "{}:", o),
+            E::INNER(r, c) => write!(f, "{}.{}", r, c),
             E::LIST(t) => fmt_list(f, t),
             E::APPLY { train, args } => {
                 write!(f, "(")?;
@@ -256,13 +372,27 @@ impl fmt::Display for E {
                 }
                 write!(f, ")")
             }
+            E::WHILE { test, body } => {
+                write!(f, "(⟳ {}", test)?;
+                for b in body {
+                    write!(f, " {}", b)?;
+                }
+                write!(f, ")")
+            }
+            E::COND { arms, otherwise } => {
+                write!(f, "(?")?;
+                for (test, then) in arms {
+                    write!(f, " {} {}", test, then)?;
+                }
+                write!(f, " {})", otherwise)
+            }
             E::ASEXP { name, rhs } => write!(f, "(: {} {})", name, rhs),
             E::UFNV { nparams, .. } => write!(f, "<fn:{}>", nparams),
         }
     }
 }
 
-fn fmt_list(f: &mut Fmt<'_>, t: &Vec<NN>) -> Res {
+fn fmt_list(f: &mut Fmt<'_>, t: &[NN]) -> Res {
     if t.is_empty() {
         return write!(f, "()");
     }
@@ -314,7 +444,7 @@ fn fmt_list(f: &mut Fmt<'_>, t: &Vec<NN>) -> Res {
 }
 
 pub fn gen_sep(sep: &str, r: &str, n: &NN) -> String {
-    if r.eq("") {
+    if r.is_empty() {
         format!("{}", n)
     } else {
         format!("{}{}{}", r, sep, n)
@@ -23,7 +23,7 @@ const MAGENTA: &str = "\x1b[35m"; // combinators
 const YELLOW: &str = "\x1b[33m"; // numbers
 const GREEN: &str = "\x1b[32m"; // strings
 const BLUE: &str = "\x1b[34m"; // brackets / parens
-const BOLD: &str = "\x1b[1m"; // special forms (λ ↻ :)
+const BOLD: &str = "\x1b[1m"; // special forms (λ ↻ ⟳ ? :)
 const GREY: &str = "\x1b[90m"; // comments
 
 // ---------------------------------------------------------------------------
@@ -41,12 +41,40 @@ const ALIASES: &[(&str, &str)] = &[
     ("mul", "×"),
     ("div", "÷"),
     ("eq", "="),
+    ("ne", "≠"),
+    ("lt", "<"),
+    ("gt", ">"),
+    ("le", "≤"),
+    ("ge", "≥"),
     ("amp", "&"),
     ("rho", "ρ"),
+    ("at", "@"),
+    ("gradeup", "⍋"),
+    ("gradedn", "⍒"),
+    ("sortup", "∧"),
+    ("sortdn", "∨"),
+    ("rev", "⌽"),
+    ("trans", "⍉"),
+    ("take", "↑"),
+    ("drop", "↓"),
+    ("joinf", "⍪"),
+    ("join", ","),
+    ("iota", "⍳"),
+    ("member", "∊"),
+    ("union", "∪"),
+    ("inter", "∩"),
+    ("without", "~"),
+    ("group", "⊔"),
     ("mon", ":"),
+    ("eachl", "⊣"),
     ("each", "ǁ"),
+    ("outer", "∘"),
+    ("conv", "⍟"),
+    ("pow", "⍣"),
+    ("key", "⌸"),
+    ("while", "⟳"),
     ("fold", "/"),
-    ("scanl", "\\"),
+    ("scan", "\\"),
     ("term", "|"),
     ("lam", "λ"),
     ("loop", "↻"),
@@ -158,7 +186,7 @@ fn collapse_train_spaces(input: &str) -> String {
             }
 
             // Check for special form heads that are NOT trains
-            if j < len && matches!(chars[j], 'λ' | '↻' | ':') {
+            if j < len && matches!(chars[j], 'λ' | '↻' | '⟳' | ':' | '?') {
                 is_train = false;
             }
 
@@ -185,9 +213,9 @@ fn collapse_train_spaces(input: &str) -> String {
 
                 if found_pipe {
                     // Emit the segment [i..end] with spaces stripped, then the `|`
-                    for k in i..end {
-                        if !chars[k].is_whitespace() {
-                            out.push(chars[k]);
+                    for &c in &chars[i..end] {
+                        if !c.is_whitespace() {
+                            out.push(c);
                         }
                     }
                     out.push('|');
@@ -243,8 +271,8 @@ impl LinkHighlighter {
                     before_ok && after_ok
                 }) {
                     let color = match *symbol {
-                        "/" | "\\" | "ǁ" => MAGENTA,
-                        "λ" | "↻" => BOLD,
+                        "/" | "\\" | "ǁ" | "⊣" | "∘" | "⍟" | "⍣" | "⌸" => MAGENTA,
+                        "λ" | "↻" | "⟳" => BOLD,
                         _ => CYAN,
                     };
                     out.push_str(color);
@@ -301,8 +329,8 @@ impl LinkHighlighter {
                 continue;
             }
 
-            // Special forms: λ ↻
-            if ch == 'λ' || ch == '↻' {
+            // Special forms: λ ↻ ⟳ ?
+            if matches!(ch, 'λ' | '↻' | '⟳' | '?') {
                 out.push_str(BOLD);
                 out.push(ch);
                 out.push_str(RESET);
@@ -311,7 +339,38 @@ impl LinkHighlighter {
             }
 
             // Operators (including unicode ones)
-            if matches!(ch, '+' | '×' | '÷' | '¯' | '=' | '&' | '!' | 'ρ') {
+            if matches!(
+                ch,
+                '+' | '×'
+                    | '÷'
+                    | '¯'
+                    | '='
+                    | '<'
+                    | '>'
+                    | '≤'
+                    | '≥'
+                    | '≠'
+                    | '&'
+                    | '!'
+                    | 'ρ'
+                    | '@'
+                    | '⍋'
+                    | '⍒'
+                    | '∧'
+                    | '∨'
+                    | '⌽'
+                    | '⍉'
+                    | '↑'
+                    | '↓'
+                    | ','
+                    | '⍪'
+                    | '⍳'
+                    | '∊'
+                    | '∪'
+                    | '∩'
+                    | '~'
+                    | '⊔'
+            ) {
                 out.push_str(CYAN);
                 out.push(ch);
                 out.push_str(RESET);
@@ -343,7 +402,7 @@ impl LinkHighlighter {
             }
 
             // Combinators
-            if matches!(ch, '/' | '\\' | 'ǁ') {
+            if matches!(ch, '/' | '\\' | 'ǁ' | '⊣' | '∘' | '⍟' | '⍣' | '⌸') {
                 out.push_str(MAGENTA);
                 out.push(ch);
                 out.push_str(RESET);
@@ -3,10 +3,10 @@ use std::collections::HashMap;
 use debug_print::debug_println;
 
 use crate::err::LErrEnum::ExprExpected as Er;
-use crate::{get_cnop, get_fnop, LErrEnum, LocatedError, URes};
+use crate::{get_cnop, get_fnop, LErr, LErrEnum, LocatedError, URes};
 
 use crate::{
-    ast::{E, NN},
+    ast::{CN, E, FN, NN},
     op::{make_op, u16_to_u8, OP},
     parse::parse,
     BRes,
@@ -51,6 +51,17 @@ impl PartialEq for B {
     }
 }
 
+/// Train elements that never take a left argument, even in a dyadic train.
+fn is_monadic_only(t: &NN) -> bool {
+    match &t.n {
+        E::MOP(_) => true,
+        E::MCO { co, .. } | E::DCO { co, .. } => {
+            matches!(co.n, E::CN(CN::Fold | CN::Scan | CN::Converge))
+        }
+        _ => false,
+    }
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct I {
@@ -92,20 +103,15 @@ impl I {
                 }
             };
             // Don't add POP after assignments or do-blocks that contain assignments
-            match n.n {
-                E::ASEXP { .. } => return,
-                _ => (),
-            };
-            match i.addop(n, OP::POP) {
-                Ok(_) => (),
-                Err(err) => {
-                    e = Some(err);
-                    return;
-                }
-            };
+            if let E::ASEXP { .. } = n.n {
+                return;
+            }
+            if let Err(err) = i.addop(n, OP::POP) {
+                e = Some(err);
+            }
         });
-        if e.is_some() {
-            return Err(e.unwrap());
+        if let Some(err) = e {
+            return Err(err);
         }
         Ok(i.b)
     }
@@ -125,20 +131,15 @@ impl I {
                     return;
                 }
             };
-            match n.n {
-                E::ASEXP { .. } => return,
-                _ => (),
-            };
-            match i.addop(n, OP::POP) {
-                Ok(_) => (),
-                Err(err) => {
-                    e = Some(err);
-                    return;
-                }
-            };
+            if let E::ASEXP { .. } = n.n {
+                return;
+            }
+            if let Err(err) = i.addop(n, OP::POP) {
+                e = Some(err);
+            }
         });
-        if e.is_some() {
-            return Err(e.unwrap());
+        if let Some(err) = e {
+            return Err(err);
         }
         Ok(i.b)
     }
@@ -151,10 +152,6 @@ impl I {
         len
     }
 
-    fn addlookup(&mut self, v: &str, n: u16) {
-        self.b.lookup.insert(v.to_owned(), n);
-    }
-
     fn addop(&mut self, n: NN, op: OP) -> URes {
         let pos = (n.start, n.end);
         self.b.op.extend(make_op(op));
@@ -229,61 +226,78 @@ impl I {
                 self.addop(n, OP::STORE(name_idx))
             }
 
-            E::DOBLOCK(exprs) => {
-                let len = exprs.len();
-                let mut last = 0u16;
-                for (i, expr) in exprs.into_iter().enumerate() {
+            E::DOBLOCK(exprs) => self.sequence(exprs, false),
+
+            E::COND { arms, otherwise } => self.cond(arms, *otherwise, false),
+
+            E::WHILE { test, body } => {
+                // The loop's value starts out as () and becomes the value of
+                // the body on each pass:
+                //   start: test, LOOPF end, POP, body, JMP start
+                //   end:
+                let ci = self.addvar(NN::nd(E::LIST(vec![])));
+                self.addop(n.clone(), OP::CONST(ci))?;
+                let start = self.b.op.len() as u16;
+                self.inode(*test.clone(), None)?;
+                let exit = self.addop(*test, OP::LOOPF(0))?;
+                self.addop(n.clone(), OP::POP)?;
+
+                let len = body.len();
+                for (i, expr) in body.into_iter().enumerate() {
                     let is_last = i == len - 1;
-                    let is_assign = matches!(&expr.n, E::ASEXP { .. });
-                    last = self.inode(expr.clone(), None)?;
-                    // POP intermediate results (but not assignments, not the last expr)
-                    if !is_last && !is_assign {
-                        self.addop(expr, OP::POP)?;
+                    self.inode(expr.clone(), None)?;
+                    match (&expr.n, is_last) {
+                        // An assignment leaves nothing behind; as the last
+                        // expression its value is the one just stored
+                        (E::ASEXP { name, .. }, true) => {
+                            let ni = self.addvar(NN::nd(E::ST(name.clone())));
+                            self.addop(expr, OP::LOAD(ni))?;
+                        }
+                        (E::ASEXP { .. }, false) | (_, true) => {}
+                        (_, false) => {
+                            self.addop(expr, OP::POP)?;
+                        }
                     }
                 }
-                Ok(last)
+
+                self.addop(n.clone(), OP::JMP(start))?;
+                let end = self.b.op.len() as u16;
+                self.upop(end, exit - 3);
+                Ok(end)
             }
 
             E::LAMBDA { params, body } => {
-                // Compile lambda as: JMP past body, then body code, then END
+                // Compile the body into its own op buffer, ending in END.
                 // The UFNV captures the body bytecode + constants so it is
                 // self-contained and works across REPL compilation units.
-
-                // Emit JMP to skip past the body (will be patched)
-                let jmp_pos = self.addop(n.clone(), OP::JMP(0))? - 3;
+                // Because the body runs in a VM of its own, block addresses
+                // inside it must be relative to the start of the body.
+                let outer_op = std::mem::take(&mut self.b.op);
+                // Names in the body never compile to CONST: they resolve at
+                // call time against the params, the captured environment and
+                // finally the globals, so hide the outer lookup meanwhile.
+                let outer_lookup = std::mem::take(&mut self.b.lookup);
 
                 let nparams = params.len();
-                let body_start = (jmp_pos + 3) as usize;
 
                 // Body: bind params from stack, execute body, leave result on stack
                 // Params are pushed in order by the caller, so we pop them in reverse
-                // NOTE: we intentionally do NOT addlookup for params here. This forces
-                // the body's references to params to use LOAD (runtime resolution)
-                // instead of CONST (which would push the name string, not the value).
+                // Params stay out of the lookup, so the body's references to them
+                // compile to LOAD (runtime resolution) instead of CONST (which
+                // would push the name string, not the value).
                 for param in params.iter().rev() {
                     let name_node = NN::nd(E::ST(param.clone()));
                     let name_idx = self.addvar(name_node);
                     self.addop(n.clone(), OP::STORE(name_idx))?;
                 }
 
-                // Compile body expressions
-                let body_len = body.len();
-                for (i, expr) in body.into_iter().enumerate() {
-                    let is_last = i == body_len - 1;
-                    let is_assign = matches!(&expr.n, E::ASEXP { .. });
-                    self.inode(expr.clone(), None)?;
-                    if !is_last && !is_assign {
-                        self.addop(expr, OP::POP)?;
-                    }
-                }
-
-                let end_pos = self.addop(n.clone(), OP::END)?;
+                // Compile body expressions; the last one is in tail position
+                self.sequence(body, true)?;
 
-                // Patch the JMP to skip to after END
-                self.upop(end_pos, jmp_pos);
+                self.addop(n.clone(), OP::END)?;
 
-                // Extract the body bytecode (from body_start up to end_pos)
-                let body_op = self.b.op[body_start..end_pos as usize].to_vec();
+                let body_op = std::mem::replace(&mut self.b.op, outer_op);
+                self.b.lookup = outer_lookup;
                 // Snapshot the full constant pool so indices remain valid
                 let body_var = self.b.var.clone();
 
@@ -291,9 +305,11 @@ impl I {
                     nparams,
                     body_op,
                     body_var,
+                    env: vec![],
                 });
                 let lambda_idx = self.addvar(lambda_node);
-                self.addop(n, OP::CONST(lambda_idx))
+                // CLOS pushes the function with the creating frame's bindings
+                self.addop(n, OP::CLOS(lambda_idx))
             }
 
             E::APPLY { train, args } => {
@@ -304,7 +320,6 @@ impl I {
                         // Monadic: push arg, then apply train
                         let rhs = args.into_iter().next().ok_or(Er)?;
                         self.inode(rhs.clone(), None)?;
-                        self.addlookup("a", (self.b.var.len().max(1) - 1) as u16);
 
                         let blp = self.addop(n.clone(), OP::MBL(0))?;
 
@@ -324,34 +339,30 @@ impl I {
                         let lhs = args_iter.next().ok_or(Er)?;
                         let rhs = args_iter.next().ok_or(Er)?;
 
-                        // For dyadic: rhs is "a" (right arg), lhs is "w" (left arg)
+                        // For dyadic: rhs is pushed first, lhs ends up on top.
+                        // Args are not bound to names: a lambda parameter
+                        // called a or w must still resolve to itself.
                         self.inode(rhs.clone(), None)?;
-                        self.addlookup("a", (self.b.var.len().max(1) - 1) as u16);
-
                         self.inode(lhs.clone(), None)?;
-                        self.addlookup("w", (self.b.var.len().max(1) - 1) as u16);
 
                         let blp = self.addop(n.clone(), OP::DBL(0))?;
 
                         // Compile train in reverse (right-to-left evaluation).
                         // In a dyadic context with a multi-element train:
-                        //   - Rightmost elements apply monadically to rhs
-                        //   - Leftmost element applies dyadically (lhs + chain result)
+                        //   - The leftmost element that can be dyadic applies
+                        //     dyadically (lhs + chain result)
+                        //   - Elements to its right apply monadically to rhs
+                        //   - Monadic-only elements to its left (a fold, or an
+                        //     op forced with `:`) apply monadically to its
+                        //     result, so (+/×| x y) is a dot product
                         // Single-element train: the one op is dyadic.
                         let train_vec: Vec<NN> = train;
-                        let train_len = train_vec.len();
-                        for (i, t) in train_vec.into_iter().rev().enumerate() {
-                            let is_last_in_reverse = i == train_len - 1;
-                            if train_len == 1 {
-                                // Single-op train: dyadic
-                                self.compile_train_elem(t, false)?;
-                            } else if is_last_in_reverse {
-                                // Leftmost element (last in reverse): dyadic
-                                self.compile_train_elem(t, false)?;
-                            } else {
-                                // Rightmost and intermediate ops: monadic
-                                self.compile_train_elem(t, true)?;
-                            }
+                        let dyadic_at = train_vec
+                            .iter()
+                            .position(|t| !is_monadic_only(t))
+                            .unwrap_or(0);
+                        for (i, t) in train_vec.into_iter().enumerate().rev() {
+                            self.compile_train_elem(t, i != dyadic_at)?;
                         }
 
                         let end = self.addop(n, OP::END)?;
@@ -359,10 +370,30 @@ impl I {
                         Ok(end)
                     }
                     _ => {
-                        // Variadic — not yet supported for built-in trains
-                        // Could be a user function call with multiple args
-                        // For now, treat as monadic with a list arg
-                        Err(LocatedError::from(Er))
+                        // Three or more args: only a single named function
+                        // can take them. Push the args in order, then the
+                        // name, and CALL resolves it at runtime.
+                        // @ takes one index per axis after the array: IDX.
+                        let name = match train.as_slice() {
+                            [NN {
+                                n: E::VAL(name), ..
+                            }] => name.clone(),
+                            [NN {
+                                n: E::MFN(FN::At), ..
+                            }] => {
+                                for a in args {
+                                    self.inode(a, None)?;
+                                }
+                                return self.addop(n, OP::IDX(nargs as u16 - 1));
+                            }
+                            _ => return Err(LocatedError::from(Er)),
+                        };
+                        for a in args {
+                            self.inode(a, None)?;
+                        }
+                        let ci = self.addvar(NN::nd(E::ST(name)));
+                        self.addop(n.clone(), OP::CONST(ci))?;
+                        self.addop(n, OP::CALL(nargs as u16))
                     }
                 }
             }
@@ -373,6 +404,7 @@ impl I {
             | E::MCO { .. }
             | E::DCO { .. }
             | E::MOP(_)
+            | E::INNER(..)
             | E::UFNV { .. } => {
                 // These should only appear inside trains, not as top-level nodes
                 Err(LocatedError::from(Er))
@@ -380,6 +412,78 @@ impl I {
         }
     }
 
+    /// Compile expressions in order, keeping only the last value.
+    /// With `tail` set the last expression is in tail position of a lambda body.
+    fn sequence(&mut self, exprs: Vec<NN>, tail: bool) -> URes {
+        let len = exprs.len();
+        let mut last = 0u16;
+        for (i, expr) in exprs.into_iter().enumerate() {
+            let is_last = i == len - 1;
+            let is_assign = matches!(&expr.n, E::ASEXP { .. });
+            last = match is_last && tail {
+                true => self.inode_tail(expr.clone())?,
+                false => self.inode(expr.clone(), None)?,
+            };
+            // POP intermediate results (but not assignments, not the last expr)
+            if !is_last && !is_assign {
+                self.addop(expr, OP::POP)?;
+            }
+        }
+        Ok(last)
+    }
+
+    /// Compile a conditional. Each test is followed by a JMPF over its
+    /// branch; each branch ends in a JMP past the else branch, so only the
+    /// chosen branch runs. With `tail` set the branches are in tail position.
+    fn cond(&mut self, arms: Vec<(NN, NN)>, otherwise: NN, tail: bool) -> URes {
+        let mut exits = vec![];
+        for (test, then) in arms {
+            self.inode(test.clone(), None)?;
+            let skip = self.addop(test, OP::JMPF(0))?;
+            match tail {
+                true => self.inode_tail(then.clone())?,
+                false => self.inode(then.clone(), None)?,
+            };
+            exits.push(self.addop(then, OP::JMP(0))?);
+            let next = self.b.op.len() as u16;
+            self.upop(next, skip - 3);
+        }
+        let last = match tail {
+            true => self.inode_tail(otherwise)?,
+            false => self.inode(otherwise, None)?,
+        };
+        let end = self.b.op.len() as u16;
+        for exit in exits {
+            self.upop(end, exit - 3);
+        }
+        Ok(last)
+    }
+
+    /// Compile an expression in tail position of a lambda body. A plain call
+    /// to a named function becomes TCALL, which reuses the running frame
+    /// instead of nesting a new one.
+    fn inode_tail(&mut self, n: NN) -> URes {
+        match n.n.clone() {
+            E::APPLY { train, args } => match train.as_slice() {
+                [NN {
+                    n: E::VAL(name), ..
+                }] => {
+                    let nargs = args.len();
+                    for a in args {
+                        self.inode(a, None)?;
+                    }
+                    let ci = self.addvar(NN::nd(E::ST(name.clone())));
+                    self.addop(n.clone(), OP::CONST(ci))?;
+                    self.addop(n, OP::TCALL(nargs as u16))
+                }
+                _ => self.inode(n, None),
+            },
+            E::DOBLOCK(exprs) => self.sequence(exprs, true),
+            E::COND { arms, otherwise } => self.cond(arms, *otherwise, true),
+            _ => self.inode(n, None),
+        }
+    }
+
     /// Compile a single train element (op, combinator, cfn, name, or monadic override)
     fn compile_train_elem(&mut self, t: NN, monadic: bool) -> URes {
         match t.n.clone() {
@@ -396,11 +500,36 @@ impl I {
                 // Monadic override: always emit as MO regardless of context
                 self.addop(t, OP::MO(get_fnop(fun)))
             }
+            E::INNER(r, c) => match monadic {
+                // Inner product needs both arguments
+                true => Err(LocatedError::from(Er)),
+                false => self.addop(t, OP::IP(get_fnop(r), get_fnop(c))),
+            },
             E::MCO { o, co } | E::DCO { o, co } => {
+                // Converge compiles to a loop, so nothing can follow it
+                if let E::MCO { co: inner, .. } | E::DCO { co: inner, .. } = &o.n {
+                    if matches!(inner.n, E::CN(CN::Converge)) {
+                        return Err(LocatedError::from(LErr {
+                            error: format!("⍟ must be the last combinator, not followed by {}", co),
+                            start: t.start,
+                            end: t.end,
+                        }));
+                    }
+                }
                 // Operator + combinator pair
                 // In monadic context, emit the op as MO then the combinator
                 // In dyadic context, emit the op as DO then the combinator
                 match monadic {
+                    true if matches!(co.n, E::CN(CN::Converge)) => {
+                        // Converge loops in the bytecode: keep the previous
+                        // value, apply the op, and go round again until the
+                        // result stops changing
+                        //   start: COPY, op, CONV start
+                        let start = self.b.op.len() as u16;
+                        self.addop(*co.clone(), OP::COPY)?;
+                        self.compile_train_elem(*o, true)?;
+                        self.addop(*co, OP::CONV(start))
+                    }
                     true => {
                         self.compile_train_elem(*o, true)?;
                         self.compile_train_elem(*co, true)
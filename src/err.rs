@@ -107,7 +107,7 @@ impl From<PestError<Rule>> for LocatedError<LErrEnum> {
     #[track_caller]
     fn from(err: PestError<Rule>) -> Self {
         LocatedError {
-            inner: LErrEnum::PestError(err),
+            inner: LErrEnum::PestError(Box::new(err)),
             location: std::panic::Location::caller(),
         }
     }
@@ -119,7 +119,7 @@ pub enum LErrEnum {
     ExprExpected,
     IntExpected(ParseIntError),
     FloatExpected(ParseFloatError),
-    PestError(PestError<Rule>),
+    PestError(Box<PestError<Rule>>),
     Rule(Rule),
     None,
 }
@@ -132,7 +132,7 @@ impl Error for LErrEnum {
 
 impl From<PestError<Rule>> for LErrEnum {
     fn from(e: PestError<Rule>) -> Self {
-        Self::PestError(e)
+        Self::PestError(Box::new(e))
     }
 }
 
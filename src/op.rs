@@ -19,6 +19,14 @@ const STORE: u8 = 16; // store variable binding: pops value, stores in lookup by
 const LOAD: u8 = 17; // load variable by name index
 const MCALL: u8 = 18; // monadic call: u16 = name index in constant pool (resolve to UFNV)
 const DCALL: u8 = 19; // dyadic call: u16 = name index in constant pool (resolve to UFNV)
+const CLOS: u8 = 20; // push closure: u16 = constant index of a UFNV, captures the frame's bindings
+const TCALL: u8 = 21; // tail call: like CALL, but replaces the running function's frame
+const JMPF: u8 = 22; // pop a test; jump to u16 address if it is false
+const COPY: u8 = 23; // push a copy of the top of the stack
+const CONV: u8 = 24; // pop new and previous value, push new; jump to u16 address unless they match
+const IP: u8 = 25; // inner product: reducing fn byte, then combining fn byte
+const IDX: u8 = 26; // pop u16 indices and the array below them, push the selection
+const LOOPF: u8 = 27; // JMPF for a while loop's test, so errors name the loop
 
 const FNPLUS: u8 = 1;
 const FNMINUS: u8 = 2;
@@ -30,10 +38,37 @@ const FNEQ: u8 = 5;
 const FNAMP: u8 = 6;
 const FNBANG: u8 = 7;
 const FNRHO: u8 = 10;
+const FNLT: u8 = 11;
+const FNGT: u8 = 12;
+const FNLE: u8 = 13;
+const FNGE: u8 = 14;
+const FNNE: u8 = 15;
+const FNAT: u8 = 16;
+const FNGRADEUP: u8 = 17;
+const FNGRADEDN: u8 = 18;
+const FNSORTUP: u8 = 19;
+const FNSORTDN: u8 = 20;
+const FNREVERSE: u8 = 21;
+const FNTRANSPOSE: u8 = 22;
+const FNTAKE: u8 = 23;
+const FNDROP: u8 = 24;
+const FNCAT: u8 = 25;
+const FNCATFIRST: u8 = 26;
+const FNINDEXOF: u8 = 27;
+const FNMEMBER: u8 = 28;
+const FNUNION: u8 = 29;
+const FNINTERSECT: u8 = 30;
+const FNWITHOUT: u8 = 31;
+const FNGROUP: u8 = 32;
 
 const COFOLD: u8 = 1;
-const COSCANL: u8 = 2;
+const COSCAN: u8 = 2;
 const COEACH: u8 = 3;
+const COCONV: u8 = 4;
+const COPOW: u8 = 5;
+const COEACHL: u8 = 6;
+const COOUTER: u8 = 7;
+const COKEY: u8 = 8;
 
 #[derive(Debug, Copy, Clone)]
 pub enum OP {
@@ -62,6 +97,14 @@ pub enum OP {
     LOAD(u16),  // load var: u16 = name index in constant pool
     MCALL(u16), // monadic user fn call: u16 = name index in constant pool
     DCALL(u16), // dyadic user fn call: u16 = name index in constant pool
+    CLOS(u16),  // push closure: u16 = constant index of a UFNV
+    TCALL(u16), // tail call: u16 = number of args
+    JMPF(u16),  // conditional jump: u16 = address taken when the test is false
+    COPY,       // push a copy of the top value
+    CONV(u16),  // converge step: u16 = loop start, taken until the value stops changing
+    IP(u8, u8), // inner product: reducing fn, combining fn
+    IDX(u16),   // index along several axes: u16 = number of indices
+    LOOPF(u16), // while test jump: u16 = address taken when the test is false
 }
 
 pub fn u16_to_u8(integer: u16) -> [u8; 2] {
@@ -99,6 +142,14 @@ pub fn get_op(op: OP) -> u8 {
         OP::LOAD(_) => LOAD,
         OP::MCALL(_) => MCALL,
         OP::DCALL(_) => DCALL,
+        OP::CLOS(_) => CLOS,
+        OP::TCALL(_) => TCALL,
+        OP::JMPF(_) => JMPF,
+        OP::COPY => COPY,
+        OP::CONV(_) => CONV,
+        OP::IP(..) => IP,
+        OP::IDX(_) => IDX,
+        OP::LOOPF(_) => LOOPF,
     }
 }
 
@@ -111,30 +162,58 @@ pub fn get_fnop(fun: FN) -> u8 {
         FN::Max => FNMAX,
         FN::Min => FNMIN,
         FN::Eq => FNEQ,
+        FN::Lt => FNLT,
+        FN::Gt => FNGT,
+        FN::Le => FNLE,
+        FN::Ge => FNGE,
+        FN::Ne => FNNE,
         FN::Amp => FNAMP,
         FN::Bang => FNBANG,
         FN::Rho => FNRHO,
+        FN::At => FNAT,
+        FN::GradeUp => FNGRADEUP,
+        FN::GradeDown => FNGRADEDN,
+        FN::SortUp => FNSORTUP,
+        FN::SortDown => FNSORTDN,
+        FN::Reverse => FNREVERSE,
+        FN::Transpose => FNTRANSPOSE,
+        FN::Take => FNTAKE,
+        FN::Drop => FNDROP,
+        FN::Cat => FNCAT,
+        FN::CatFirst => FNCATFIRST,
+        FN::IndexOf => FNINDEXOF,
+        FN::Member => FNMEMBER,
+        FN::Union => FNUNION,
+        FN::Intersect => FNINTERSECT,
+        FN::Without => FNWITHOUT,
+        FN::Group => FNGROUP,
     }
 }
 
 pub fn get_cnop(cn: CN) -> u8 {
     match cn {
         CN::Fold => COFOLD,
-        CN::ScanL => COSCANL,
+        CN::Scan => COSCAN,
+        CN::EachLeft => COEACHL,
+        CN::Outer => COOUTER,
         CN::Each => COEACH,
+        CN::Converge => COCONV,
+        CN::Power => COPOW,
+        CN::Key => COKEY,
     }
 }
 
 pub fn make_op(op: OP) -> Vec<u8> {
     let code = get_op(op);
     match op {
-        OP::POP | OP::CLVAR | OP::CRVAR | OP::GETL | OP::GETR | OP::END => {
+        OP::POP | OP::CLVAR | OP::CRVAR | OP::GETL | OP::GETR | OP::END | OP::COPY => {
             vec![code]
         }
 
         OP::MO(a) => vec![MO, a],
         OP::DO(a) => vec![DO, a],
         OP::CO(a) => vec![CO, a],
+        OP::IP(f, g) => vec![IP, f, g],
 
         OP::CONST(a)
         | OP::JMP(a)
@@ -145,7 +224,13 @@ pub fn make_op(op: OP) -> Vec<u8> {
         | OP::STORE(a)
         | OP::LOAD(a)
         | OP::MCALL(a)
-        | OP::DCALL(a) => make_o(code, a),
+        | OP::DCALL(a)
+        | OP::CLOS(a)
+        | OP::TCALL(a)
+        | OP::JMPF(a)
+        | OP::CONV(a)
+        | OP::IDX(a)
+        | OP::LOOPF(a) => make_o(code, a),
     }
 }
 
@@ -172,6 +257,14 @@ pub fn byte_to_op(by: u8) -> Option<OP> {
         LOAD => Some(OP::LOAD(0)),
         MCALL => Some(OP::MCALL(0)),
         DCALL => Some(OP::DCALL(0)),
+        CLOS => Some(OP::CLOS(0)),
+        TCALL => Some(OP::TCALL(0)),
+        JMPF => Some(OP::JMPF(0)),
+        COPY => Some(OP::COPY),
+        CONV => Some(OP::CONV(0)),
+        IP => Some(OP::IP(0, 0)),
+        IDX => Some(OP::IDX(0)),
+        LOOPF => Some(OP::LOOPF(0)),
 
         _ => None,
     }
@@ -186,25 +279,45 @@ pub fn byte_to_fn(by: u8) -> FN {
         FNMAX => FN::Max,
         FNMIN => FN::Min,
         FNEQ => FN::Eq,
+        FNLT => FN::Lt,
+        FNGT => FN::Gt,
+        FNLE => FN::Le,
+        FNGE => FN::Ge,
+        FNNE => FN::Ne,
         FNAMP => FN::Amp,
         FNBANG => FN::Bang,
         FNRHO => FN::Rho,
+        FNAT => FN::At,
+        FNGRADEUP => FN::GradeUp,
+        FNGRADEDN => FN::GradeDown,
+        FNSORTUP => FN::SortUp,
+        FNSORTDN => FN::SortDown,
+        FNREVERSE => FN::Reverse,
+        FNTRANSPOSE => FN::Transpose,
+        FNTAKE => FN::Take,
+        FNDROP => FN::Drop,
+        FNCAT => FN::Cat,
+        FNCATFIRST => FN::CatFirst,
+        FNINDEXOF => FN::IndexOf,
+        FNMEMBER => FN::Member,
+        FNUNION => FN::Union,
+        FNINTERSECT => FN::Intersect,
+        FNWITHOUT => FN::Without,
+        FNGROUP => FN::Group,
         _ => unreachable!("unknown fn code"),
     }
 }
 
 pub fn byte_to_co(by: u8) -> CN {
     match by {
-        COSCANL => CN::ScanL,
+        COSCAN => CN::Scan,
+        COEACHL => CN::EachLeft,
+        COOUTER => CN::Outer,
         COFOLD => CN::Fold,
         COEACH => CN::Each,
+        COCONV => CN::Converge,
+        COPOW => CN::Power,
+        COKEY => CN::Key,
         _ => unreachable!("Expected CN"),
     }
 }
-
-pub fn op_to_co(op: OP, co: u8) -> Option<CN> {
-    match op {
-        OP::CO(_) => Some(byte_to_co(co)),
-        _ => None,
-    }
-}
@@ -27,7 +27,8 @@ pub fn pf(source: &str) -> NN {
 }
 
 /// Build an AST node from a sexpr pair.
-/// A sexpr is either an atom or a parenthesized (inner).
+/// A sexpr is either an atom or a parenthesized (inner), optionally
+/// followed by bracket indices.
 fn build_sexpr(pair: Pair) -> Res {
     debug_println!(
         "build_sexpr: rule={:?} str={}",
@@ -36,14 +37,22 @@ fn build_sexpr(pair: Pair) -> Res {
     );
     match pair.as_rule() {
         Rule::sexpr => {
-            // sexpr = { "(" ~ inner ~ ")" | atom }
+            // sexpr = { base ~ index* }
+            let mut parts = pair.into_inner();
+            let base = build_sexpr(parts.next().ok_or(Er)?)?;
+            parts.try_fold(base, build_index)
+        }
+        Rule::base => {
+            // base = { "(" ~ inner ~ ")" | atom }
             // inner is silent, so we see its children directly
             let inner = pair.clone().into_inner().next().ok_or(Er)?;
             match inner.as_rule() {
                 // If the inner is one of the named rules, build accordingly
                 Rule::lambda => build_lambda(pair, inner),
                 Rule::doblock => build_doblock(pair, inner),
+                Rule::whileloop => build_while(pair, inner),
                 Rule::assign => build_assign(pair, inner),
+                Rule::cond => build_cond(pair, inner),
                 Rule::apply => build_apply(pair, inner),
                 Rule::list_inner => build_list(pair, inner),
                 // Atoms
@@ -65,6 +74,28 @@ fn build_sexpr(pair: Pair) -> Res {
     }
 }
 
+/// x[i;j...] desugars to (@| x i j...)
+fn build_index(target: NN, pair: Pair) -> Res {
+    let start = target.start;
+    let end = pair.as_span().end();
+    let at = NN::new(pair.clone(), E::MFN(FN::At));
+    let indices: Vec<NN> = pair
+        .into_inner()
+        .map(|ix| build_sexpr(ix.into_inner().next().ok_or(Er)?))
+        .collect::<Result<_, _>>()?;
+
+    let mut args = vec![target];
+    args.extend(indices);
+    Ok(NN {
+        n: E::APPLY {
+            train: vec![at],
+            args,
+        },
+        start,
+        end,
+    })
+}
+
 /// (λ (params...) body...)
 fn build_lambda(outer: Pair, pair: Pair) -> Res {
     let mut inner = pair.into_inner();
@@ -94,6 +125,21 @@ fn build_doblock(outer: Pair, pair: Pair) -> Res {
     Ok(NN::new(outer, E::DOBLOCK(exprs?)))
 }
 
+/// (⟳ test body...)
+fn build_while(outer: Pair, pair: Pair) -> Res {
+    let mut inner = pair.into_inner();
+    let test = build_sexpr(inner.next().ok_or(Er)?)?;
+    let body: Result<Vec<NN>, _> = inner.map(build_sexpr).collect();
+
+    Ok(NN::new(
+        outer,
+        E::WHILE {
+            test: Box::new(test),
+            body: body?,
+        },
+    ))
+}
+
 /// (: name expr)
 fn build_assign(outer: Pair, pair: Pair) -> Res {
     let mut inner = pair.into_inner();
@@ -109,6 +155,30 @@ fn build_assign(outer: Pair, pair: Pair) -> Res {
     ))
 }
 
+/// (? test1 then1 test2 then2 ... else)
+fn build_cond(outer: Pair, pair: Pair) -> Res {
+    let mut exprs: Vec<NN> = pair
+        .into_inner()
+        .map(build_sexpr)
+        .collect::<Result<_, _>>()?;
+
+    // The grammar guarantees an odd count: the last one is the else branch
+    let otherwise = exprs.pop().ok_or(Er)?;
+    let mut exprs = exprs.into_iter();
+    let mut arms = Vec::new();
+    while let (Some(test), Some(then)) = (exprs.next(), exprs.next()) {
+        arms.push((test, then));
+    }
+
+    Ok(NN::new(
+        outer,
+        E::COND {
+            arms,
+            otherwise: Box::new(otherwise),
+        },
+    ))
+}
+
 /// (train args...)
 /// train is a sequence of ops/combinators/names
 fn build_apply(outer: Pair, pair: Pair) -> Res {
@@ -125,7 +195,7 @@ fn build_apply(outer: Pair, pair: Pair) -> Res {
 }
 
 /// Build train elements from a train pair.
-/// A train is: (mop | cfn | op | cn | ident)+
+/// A train is: (mop | ip | cfn | op | cn | ident)+
 fn build_train(pair: Pair) -> Result<Vec<NN>, LocatedError<LErrE>> {
     let mut elems = Vec::new();
     for child in pair.into_inner() {
@@ -147,11 +217,20 @@ fn build_train(pair: Pair) -> Result<Vec<NN>, LocatedError<LErrE>> {
                 let op_pair = child.clone().into_inner().next().ok_or(Er)?;
                 elems.push(NN::new(child, E::MOP(FN::from_string(op_pair.as_str()))));
             }
+            Rule::ip => {
+                // ip = { op ~ "." ~ op }
+                let mut ops = child.clone().into_inner();
+                let r = ops.next().ok_or(Er)?;
+                let c = ops.next().ok_or(Er)?;
+                elems.push(NN::new(
+                    child,
+                    E::INNER(FN::from_string(r.as_str()), FN::from_string(c.as_str())),
+                ));
+            }
             Rule::cfn => {
-                // cfn = { (op | ident) ~ cn }
+                // cfn = { (op | ident) ~ cn+ }
                 let mut cfn_inner = child.clone().into_inner();
                 let op_part = cfn_inner.next().ok_or(Er)?;
-                let cn_part = cfn_inner.next().ok_or(Er)?;
 
                 let o: Box<NN> = match op_part.as_rule() {
                     Rule::op => Box::new(NN::new(
@@ -164,13 +243,25 @@ fn build_train(pair: Pair) -> Result<Vec<NN>, LocatedError<LErrE>> {
                     )),
                     _ => return Err(LocatedError::from(Er)),
                 };
-                let co = Box::new(NN::new(
-                    cn_part.clone(),
-                    E::CN(CN::from_string(cn_part.as_str())),
-                ));
                 // In a train context we don't know arity yet — use MCO
-                // The compiler/VM will resolve based on actual arg count
-                elems.push(NN::new(child, E::MCO { o, co }));
+                // The compiler/VM will resolve based on actual arg count.
+                // Each further combinator takes the function derived so
+                // far, so +/⌸ is (+/)⌸
+                let mut derived = *o;
+                for cn_part in cfn_inner {
+                    let co = Box::new(NN::new(
+                        cn_part.clone(),
+                        E::CN(CN::from_string(cn_part.as_str())),
+                    ));
+                    derived = NN::new(
+                        child.clone(),
+                        E::MCO {
+                            o: Box::new(derived),
+                            co,
+                        },
+                    );
+                }
+                elems.push(derived);
             }
             Rule::ident => {
                 elems.push(NN::new(child.clone(), E::VAL(child.as_str().to_string())));
@@ -1,5 +1,7 @@
-use std::cmp;
+use std::cmp::{self, Ordering};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
 use debug_print::debug_println;
 use rayon::prelude::*;
@@ -7,12 +9,19 @@ use rayon::prelude::*;
 use crate::{
     ast::{CN, E, FN, NN},
     byte::{Env, B},
-    byte_to_fn,
+    byte_to_co, byte_to_fn,
     op::{byte_to_op, u8_to_u, OP},
-    op_to_co,
 };
 
 const STACK_SIZE: usize = 512;
+// Default limit on nested user function calls. Each level runs a nested VM
+// on the Rust stack, which grows onto the heap as needed (see run_ufnv), so
+// the limit holds on any thread, however small its stack.
+const MAX_DEPTH: usize = 500;
+// Before a nested call, make sure this much Rust stack is left, and if not
+// continue on a fresh segment of STACK_SEGMENT bytes
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
 
 // ---------------------------------------------------------------------------
 // VM error type
@@ -49,6 +58,15 @@ fn type_name(n: &NN) -> &'static str {
     }
 }
 
+/// Read a test value as a truth value: bools, or ints where nonzero is true.
+fn truth(n: &NN) -> Option<bool> {
+    match n.n {
+        E::BOOL(b) => Some(b),
+        E::INT(i) => Some(i != 0),
+        _ => None,
+    }
+}
+
 pub type VmRes = Result<NN, VMError>;
 
 type MonadicFn = fn(&NN) -> VmRes;
@@ -82,25 +100,43 @@ impl C {
 // VM
 // ---------------------------------------------------------------------------
 
-pub struct V {
+pub struct V<'a> {
     b: B,
+    // Globals of the top-level frame; None when this is the top-level frame
+    root: Option<&'a B>,
+    // Bindings captured by the closure this frame is running
+    env: Vec<(String, NN)>,
+    // The function this frame is running, bound to the name it was called by
+    this: Option<(&'a str, &'a NN)>,
+    // Nesting of user function calls; exceeding max_depth is an error
+    depth: usize,
+    pub max_depth: usize,
+    // Set by TCALL: the call that replaces this frame once it stops
+    tail: Option<TailCall>,
 
     s: Vec<NN>,
     last_popped: Option<NN>,
     pub error: Option<VMError>,
 
-    context: [C; STACK_SIZE],
+    // Boxed so that frames of deeply recursive calls stay small
+    context: Box<[C; STACK_SIZE]>,
     cptr: usize,
 }
 
-impl V {
+impl<'a> V<'a> {
     pub fn new(b: B) -> Self {
         Self {
             b,
+            root: None,
+            env: vec![],
+            this: None,
+            depth: 0,
+            max_depth: MAX_DEPTH,
+            tail: None,
             s: Vec::with_capacity(STACK_SIZE),
             last_popped: None,
             error: None,
-            context: unsafe { std::mem::zeroed() },
+            context: Box::new(unsafe { std::mem::zeroed() }),
             cptr: 0,
         }
     }
@@ -144,12 +180,26 @@ impl V {
                 }
                 OP::JMP(_) => {
                     debug_println!("\n\n-------- JMP --------");
-
-                    let us = self.get_usize(iaddr);
-                    self.cpush(C::new(us, BL::JMP));
-
-                    let i = self.get_usize(iaddr);
-                    ip = i as usize;
+                    ip = self.get_usize(iaddr);
+                }
+                OP::JMPF(_) | OP::LOOPF(_) => {
+                    debug_println!("\n\n-------- JMPF --------");
+                    let test = self.pop();
+                    let truth = truth(&test).ok_or_else(|| {
+                        let form = match op {
+                            OP::LOOPF(_) => "⟳",
+                            _ => "?",
+                        };
+                        VMError::new(format!(
+                            "{} expects a boolean test, got {}",
+                            form,
+                            type_name(&test)
+                        ))
+                    })?;
+                    ip = match truth {
+                        true => ip + 2,
+                        false => self.get_usize(iaddr),
+                    };
                 }
                 OP::DUP(_) => {
                     debug_println!("\n\n-------- DUP --------");
@@ -200,15 +250,11 @@ impl V {
                         _ => None,
                     };
 
-                    let co = byte_to_op(self.b.op[ip + 1]).unwrap();
-                    let co = op_to_co(co, self.b.op[ip + 2]);
+                    let cos = self.read_cos(ip + 1);
 
-                    self.cmo(co, mo, ip)?;
+                    self.cmo(&cos, mo, ip)?;
 
-                    match co {
-                        Some(_) => ip += 3,
-                        None => ip += 1,
-                    }
+                    ip += 1 + 2 * cos.len();
 
                     // Restore the stashed value after the monadic op
                     if let Some(stashed) = dbl_stashed {
@@ -219,22 +265,23 @@ impl V {
                     let dfn = byte_to_fn(self.b.op[ip]);
                     debug_println!("\n\n-------- DO {} --------", dfn);
 
-                    let co = byte_to_op(self.b.op[ip + 1]).unwrap();
-                    let co = op_to_co(co, self.b.op[ip + 2]);
+                    let cos = self.read_cos(ip + 1);
 
-                    self.cdo(co, dfn, ip)?;
+                    self.cdo(&cos, dfn, ip)?;
 
                     self.dup();
 
-                    match co {
-                        Some(_) => ip += 3,
-                        None => ip += 1,
-                    }
+                    ip += 1 + 2 * cos.len();
                 }
                 OP::CLVAR => {
                     debug_println!("\n\n-------- CLVAR --------");
                     self.pop();
                 }
+                OP::END if self.cptr == 0 => {
+                    // END outside any block closes a function body
+                    debug_println!("\n\n-------- FNEND --------");
+                    break;
+                }
                 OP::END => {
                     debug_println!("\n\n-------- END --------");
 
@@ -243,36 +290,19 @@ impl V {
                     match c.t {
                         BL::DBL => {
                             debug_println!("\n\n-------- DBLEND --------");
+                            // Stack is [rhs, lhs, res, dup]: keep only res
                             self.pop();
                             let res = self.pop();
                             self.pop();
-
-                            let c = self.cget();
-                            match c.t {
-                                BL::DBL => {
-                                    let rhs = self.pop();
-                                    self.push(res);
-                                    self.push(rhs);
-                                }
-                                _ => {
-                                    self.push(res);
-                                }
-                            }
+                            self.pop();
+                            self.push(res);
                         }
                         BL::MBL => {
                             debug_println!("\n\n-------- MBLEND --------");
+                            // Stack is [arg, res]: keep only res
                             let res = self.pop();
                             self.pop();
-                            match c.t {
-                                BL::DBL => {
-                                    let rhs = self.pop();
-                                    self.push(res);
-                                    self.push(rhs);
-                                }
-                                _ => {
-                                    self.push(res);
-                                }
-                            }
+                            self.push(res);
                         }
 
                         _ => (),
@@ -311,10 +341,16 @@ impl V {
                     let val = self.pop();
                     debug_println!("STORE: {} = {}", name, val);
 
-                    // Store the value in the constant pool and update lookup
-                    let val_idx = self.b.var.len() as u16;
-                    self.b.var.push(val);
-                    self.b.lookup.insert(name, val_idx);
+                    // Rebinding a name overwrites its slot, so code compiled
+                    // against the slot (and loops) see the new value
+                    match self.b.lookup.get(&name) {
+                        Some(idx) => self.b.var[*idx as usize] = val,
+                        None => {
+                            let val_idx = self.b.var.len() as u16;
+                            self.b.var.push(val);
+                            self.b.lookup.insert(name, val_idx);
+                        }
+                    }
                 }
                 OP::LOAD(_) => {
                     debug_println!("\n\n-------- LOAD --------");
@@ -330,33 +366,40 @@ impl V {
                     };
 
                     // Look up the value
-                    let val_idx = self
-                        .b
-                        .lookup
-                        .get(&name)
+                    let val = self
+                        .resolve(&name)
                         .ok_or_else(|| VMError::new(format!("undefined variable: {}", name)))?
-                        .to_owned();
-                    self.push(self.b.var[val_idx as usize].clone());
+                        .clone();
+                    self.push(val);
                 }
                 OP::CALL(_) => {
                     debug_println!("\n\n-------- CALL --------");
-                    let _nargs = u8_to_u(self.b.op[ip], self.b.op[ip + 1]);
-                    // ip += 2; // would advance past args, but we return early
-                    // TODO: implement function call
-                    return Err(VMError::new("CALL not yet implemented"));
+                    let nargs = u8_to_u(self.b.op[ip], self.b.op[ip + 1]);
+                    ip += 2;
+
+                    // The function name sits on top of its args
+                    let name = self.pop();
+                    let at = self.s.len() - nargs;
+                    let args = self.s.split_off(at);
+                    let f = match name.n {
+                        E::ST(name) => self.lookup_ufnv(name, "CALL")?,
+                        other => {
+                            return Err(VMError::new(format!(
+                                "CALL: expected function name, got {}",
+                                type_name(&NN::nd(other))
+                            )))
+                        }
+                    };
+                    let result = f.call(args)?;
+                    self.push(result);
                 }
                 OP::MCALL(_) => {
                     debug_println!("\n\n-------- MCALL --------");
                     let name_idx = u8_to_u(self.b.op[ip], self.b.op[ip + 1]);
                     ip += 2;
 
-                    let (nparams, body_op, body_var) = self.resolve_ufnv(name_idx, "MCALL")?;
-                    if nparams != 1 {
-                        return Err(VMError::new(format!(
-                            "MCALL: function expects {} args, got 1",
-                            nparams
-                        )));
-                    }
+                    let cos = self.read_cos(ip);
+                    ip += 2 * cos.len();
 
                     // In a DBL context, temporarily hide the extra duplicated value
                     let dbl_stashed = match self.cget().t {
@@ -366,7 +409,11 @@ impl V {
 
                     // Pop the arg, run the function body, push the result
                     let arg = self.pop();
-                    let result = Self::run_ufnv(&body_op, &body_var, vec![arg])?;
+                    let f = self.resolve_ufnv(name_idx, "MCALL")?;
+                    let result =
+                        self.mo_through(&cos, &arg, &|a| f.call(vec![a.clone()]), &|w, a| {
+                            f.call(vec![w.clone(), a.clone()])
+                        })?;
                     self.push(result);
 
                     // Restore stashed value
@@ -379,62 +426,243 @@ impl V {
                     let name_idx = u8_to_u(self.b.op[ip], self.b.op[ip + 1]);
                     ip += 2;
 
-                    let (nparams, body_op, body_var) = self.resolve_ufnv(name_idx, "DCALL")?;
-                    if nparams != 2 {
-                        return Err(VMError::new(format!(
-                            "DCALL: function expects {} args, got 2",
-                            nparams
-                        )));
-                    }
+                    let cos = self.read_cos(ip);
+                    ip += 2 * cos.len();
 
                     // Pop both args (lhs on top, rhs below)
                     let lhs = self.pop();
                     let rhs = self.pop();
-                    let result = Self::run_ufnv(&body_op, &body_var, vec![lhs, rhs])?;
+                    let f = self.resolve_ufnv(name_idx, "DCALL")?;
+                    let result = self.do_through(
+                        &cos,
+                        &lhs,
+                        &rhs,
+                        &|a| f.call(vec![a.clone()]),
+                        &|w, a| f.call(vec![w.clone(), a.clone()]),
+                    )?;
                     self.push(result);
 
                     // Dyadic ops dup the result for the next train element
                     self.dup();
                 }
+                OP::TCALL(_) => {
+                    debug_println!("\n\n-------- TCALL --------");
+                    let nargs = u8_to_u(self.b.op[ip], self.b.op[ip + 1]);
+
+                    // Same layout as CALL: the function name on top of its args
+                    let name = self.pop();
+                    let at = self.s.len() - nargs;
+                    let args = self.s.split_off(at);
+                    let call = match name.n {
+                        E::ST(name) => {
+                            let f = self.lookup_ufnv(name, "TCALL")?;
+                            f.check_arity(args.len())?;
+                            TailCall {
+                                name: f.name,
+                                val: f.val.clone(),
+                                args,
+                            }
+                        }
+                        other => {
+                            return Err(VMError::new(format!(
+                                "TCALL: expected function name, got {}",
+                                type_name(&NN::nd(other))
+                            )))
+                        }
+                    };
+
+                    // Stop here: run_ufnv runs the call in place of this frame
+                    self.tail = Some(call);
+                    break;
+                }
+                OP::COPY => {
+                    debug_println!("\n\n-------- COPY --------");
+                    self.dbl_stashed(|vm| {
+                        vm.dup();
+                        Ok(())
+                    })?;
+                }
+                OP::CONV(_) => {
+                    debug_println!("\n\n-------- CONV --------");
+                    let converged = self.dbl_stashed(|vm| {
+                        let new = vm.pop();
+                        let prev = vm.pop();
+                        let converged = new == prev;
+                        vm.push(new);
+                        Ok(converged)
+                    })?;
+                    ip = match converged {
+                        true => ip + 2,
+                        false => self.get_usize(iaddr),
+                    };
+                }
+                OP::IP(..) => {
+                    let rfn = byte_to_fn(self.b.op[ip]);
+                    let cfn = byte_to_fn(self.b.op[ip + 1]);
+                    debug_println!("\n\n-------- IP {}.{} --------", rfn, cfn);
+
+                    let lhs = self.pop();
+                    let rhs = self.pop();
+                    let (_, rdy) = Self::get_fun(rfn);
+                    let (_, cdy) = Self::get_fun(cfn);
+                    let result = inner(&lhs, &rhs, &rdy, &cdy)?;
+                    self.push(result);
+
+                    // Like DO, dup the result for the next train element
+                    self.dup();
+                    ip += 2;
+                }
+                OP::IDX(_) => {
+                    debug_println!("\n\n-------- IDX --------");
+                    let naxes = u8_to_u(self.b.op[ip], self.b.op[ip + 1]);
+
+                    // The array sits below one index per axis
+                    let at = self.s.len() - naxes;
+                    let indices = self.s.split_off(at);
+                    let x = self.pop();
+                    self.push(index(&x, &indices)?);
+                    ip += 2;
+                }
+                OP::CLOS(_) => {
+                    debug_println!("\n\n-------- CLOS --------");
+                    let const_idx = u8_to_u(self.b.op[ip], self.b.op[ip + 1]);
+                    ip += 2;
+                    let mut f = self.b.var[const_idx].clone();
+                    if let E::UFNV { env, .. } = &mut f.n {
+                        *env = self.capture();
+                    }
+                    self.push(f);
+                }
                 _ => return Err(VMError::new(format!("unimplemented instruction: {:?}", op))),
             }
         }
         Ok(())
     }
 
-    /// Resolve a constant-pool name index to a UFNV, returning (nparams, body_op, body_var).
-    fn resolve_ufnv(
-        &self,
-        name_idx: usize,
-        ctx: &str,
-    ) -> Result<(usize, Vec<u8>, Vec<NN>), VMError> {
-        let name = match &self.b.var[name_idx].n {
-            E::ST(s) => s.clone(),
-            _ => {
-                return Err(VMError::new(format!(
-                    "{}: expected string name in constant pool",
-                    ctx
-                )))
-            }
+    /// Run a stack operation under the value a DBL block keeps on top,
+    /// the way MO does.
+    fn dbl_stashed<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, VMError>,
+    ) -> Result<T, VMError> {
+        let stashed = match self.cget().t {
+            BL::DBL => Some(self.s.pop().expect("stack underflow")),
+            _ => None,
         };
-        let val_idx = self
+        let res = f(self)?;
+        if let Some(stashed) = stashed {
+            self.s.push(stashed);
+        }
+        Ok(res)
+    }
+
+    /// Resolve a name: this frame's bindings first, then the running
+    /// function's own name, then the environment captured by the running
+    /// closure, then the globals.
+    fn resolve(&self, name: &str) -> Option<&NN> {
+        if let Some(idx) = self.b.lookup.get(name) {
+            return Some(&self.b.var[*idx as usize]);
+        }
+        if let Some((this, f)) = self.this {
+            if this == name {
+                return Some(f);
+            }
+        }
+        if let Some((_, val)) = self.env.iter().find(|(n, _)| n == name) {
+            return Some(val);
+        }
+        let root = self.root?;
+        root.lookup.get(name).map(|idx| &root.var[*idx as usize])
+    }
+
+    /// The bindings a closure created in this frame gets to keep. Globals
+    /// are left out: they are looked up at call time, so a function can
+    /// use anything defined after it, itself included.
+    fn capture(&self) -> Vec<(String, NN)> {
+        if self.root.is_none() {
+            return vec![];
+        }
+        let mut env: Vec<(String, NN)> = self
             .b
             .lookup
-            .get(&name)
-            .ok_or_else(|| VMError::new(format!("undefined variable: {}", name)))?
-            .to_owned();
-        let fn_val = &self.b.var[val_idx as usize];
-        match &fn_val.n {
-            E::UFNV {
-                nparams,
-                body_op,
-                body_var,
-            } => Ok((*nparams, body_op.clone(), body_var.clone())),
+            .iter()
+            .map(|(name, idx)| (name.clone(), self.b.var[*idx as usize].clone()))
+            .collect();
+        env.extend(
+            self.env
+                .iter()
+                .filter(|(name, _)| !self.b.lookup.contains_key(name))
+                .cloned(),
+        );
+        env
+    }
+
+    /// Resolve a constant-pool name index to a UFNV.
+    fn resolve_ufnv(&self, name_idx: usize, ctx: &str) -> Result<UserFn<'_>, VMError> {
+        match &self.b.var[name_idx].n {
+            E::ST(s) => self.lookup_ufnv(s.clone(), ctx),
             _ => Err(VMError::new(format!(
-                "{}: {} is not a function, got {}",
-                ctx,
+                "{}: expected string name in constant pool",
+                ctx
+            ))),
+        }
+    }
+
+    /// Look a function up by name in the current bindings.
+    fn lookup_ufnv(&self, name: String, ctx: &str) -> Result<UserFn<'_>, VMError> {
+        let fn_val = self
+            .resolve(&name)
+            .ok_or_else(|| VMError::new(format!("undefined variable: {}", name)))?;
+        let kind = type_name(fn_val);
+        self.user_fn(name.clone(), fn_val).ok_or_else(|| {
+            VMError::new(format!("{}: {} is not a function, got {}", ctx, name, kind))
+        })
+    }
+
+    /// Wrap a function value so it can be called from this frame.
+    fn user_fn<'s>(&'s self, name: String, val: &'s NN) -> Option<UserFn<'s>> {
+        match &val.n {
+            E::UFNV { nparams, .. } => Some(UserFn {
                 name,
-                type_name(fn_val)
+                val,
+                nparams: *nparams,
+                root: self.root.unwrap_or(&self.b),
+                depth: self.depth + 1,
+                max_depth: self.max_depth,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Power (⍣): apply `mo` to rhs lhs times, or, when lhs is a function,
+    /// until that predicate holds for the current value.
+    fn power(&self, lhs: &NN, rhs: &NN, mo: &dyn Fn(&NN) -> VmRes) -> VmRes {
+        match &lhs.n {
+            E::INT(n) if *n < 0 => Err(VMError::new(format!(
+                "⍣ count must not be negative, got {}",
+                n
+            ))),
+            E::INT(n) => (0..*n).try_fold(rhs.clone(), |acc, _| mo(&acc)),
+            E::UFNV { .. } => {
+                let pred = self.user_fn("⍣ predicate".to_string(), lhs).unwrap();
+                let mut acc = rhs.clone();
+                loop {
+                    let test = pred.call(vec![acc.clone()])?;
+                    match truth(&test) {
+                        Some(true) => return Ok(acc),
+                        Some(false) => acc = mo(&acc)?,
+                        None => {
+                            return Err(VMError::new(format!(
+                                "⍣ predicate must return a boolean, got {}",
+                                type_name(&test)
+                            )))
+                        }
+                    }
+                }
+            }
+            _ => Err(VMError::new(format!(
+                "⍣ expects a count or a predicate function, got {}",
+                type_name(lhs)
             ))),
         }
     }
@@ -443,142 +671,180 @@ impl V {
     /// Arguments are placed on the stack (in order) before execution.
     /// The body's STORE instructions will pop them and bind to param names.
     /// Returns the last value left on the stack.
-    fn run_ufnv(body_op: &[u8], body_var: &[NN], args: Vec<NN>) -> VmRes {
-        use std::collections::HashMap;
-
-        let b = B {
-            op: body_op.to_vec(),
-            var: body_var.to_vec(),
-            lookup: HashMap::new(),
-            code: HashMap::new(),
-        };
-        let mut vm = V::new(b);
-        // Push args onto the stack (the body's STORE instructions will pop them)
-        for arg in args {
-            vm.push(arg);
+    /// A tail call leaves the frame with its next call instead of a result;
+    /// that call then runs here at the same depth, in a loop rather than by
+    /// recursion.
+    fn run_ufnv(f: &UserFn, args: Vec<NN>) -> VmRes {
+        if f.depth > f.max_depth {
+            return Err(VMError::new(format!(
+                "recursion depth limit ({}) exceeded",
+                f.max_depth
+            )));
         }
-        vm.run()
-            .map_err(|e| VMError::new(format!("in user function: {}", e.msg)))?;
-        // The result is the last value on the stack
-        Ok(vm.pop())
-    }
-
-    pub fn cmo(&mut self, co: Option<CN>, fun: FN, _ip: usize) -> Result<(), VMError> {
-        match co {
-            None => {
-                let rhs = self.pop();
-                debug_println!("cmo: rhs: {}", rhs);
-                let (fun, _) = Self::get_fun(fun);
-                let result = fun(&rhs)?;
-                self.push(result);
-            }
-            Some(CN::Fold) => {
-                let rhs = self.pop();
-                debug_println!("cmo: rhs: {}", rhs);
-                let (_, fun) = Self::get_fun(fun);
-                match rhs.n {
-                    E::LIST(l) => {
-                        let mut iter = l.into_iter();
-                        let first = iter
-                            .next()
-                            .ok_or_else(|| VMError::new("fold on empty list"))?;
-                        let result = iter.try_fold(first, |acc, a| fun(&acc, &a))?;
-                        self.push(result);
-                    }
-                    _ => {
-                        return Err(VMError::new(format!(
-                            "fold (/) expects a list, got {}",
-                            type_name(&rhs)
-                        )));
-                    }
-                };
+        let mut args = args;
+        let mut tail: Option<(String, NN)> = None;
+        loop {
+            let (name, val) = match &tail {
+                Some((name, val)) => (name.as_str(), val),
+                None => (f.name.as_str(), f.val),
+            };
+            let E::UFNV {
+                body_op,
+                body_var,
+                env,
+                ..
+            } = &val.n
+            else {
+                unreachable!("user function value is not a UFNV")
+            };
+            let b = B {
+                op: body_op.to_vec(),
+                var: body_var.to_vec(),
+                lookup: HashMap::new(),
+                code: HashMap::new(),
+            };
+            let mut vm = V::new(b);
+            vm.root = Some(f.root);
+            vm.env = env.to_vec();
+            vm.this = Some((name, val));
+            vm.depth = f.depth;
+            vm.max_depth = f.max_depth;
+            // Push args onto the stack (the body's STORE instructions will pop them)
+            for arg in std::mem::take(&mut args) {
+                vm.push(arg);
             }
-            Some(other) => {
-                return Err(VMError::new(format!(
-                    "combinator {} not supported in monadic context",
-                    other
-                )));
+            // Errors from nested calls already carry the prefix
+            stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || vm.run()).map_err(|e| match e
+                .msg
+                .starts_with("in user function: ")
+            {
+                true => e,
+                false => VMError::new(format!("in user function: {}", e.msg)),
+            })?;
+            match vm.tail.take() {
+                Some(call) => {
+                    args = call.args;
+                    tail = Some((call.name, call.val));
+                }
+                // The result is the last value on the stack
+                None => return Ok(vm.pop()),
             }
         }
-        Ok(())
     }
 
-    pub fn cdo(&mut self, co: Option<CN>, fun: FN, _ip: usize) -> Result<(), VMError> {
+    /// The combinators after a function, innermost first: +/⌸ is the op
+    /// followed by CO(/) and CO(⌸).
+    fn read_cos(&self, mut ip: usize) -> Vec<CN> {
+        let mut cos = vec![];
+        while let Some(OP::CO(_)) = self.b.op.get(ip).and_then(|b| byte_to_op(*b)) {
+            cos.push(byte_to_co(self.b.op[ip + 1]));
+            ip += 2;
+        }
+        cos
+    }
+
+    /// Apply a function monadically through its combinators. Each one takes
+    /// the function derived by those before it, so +/⌸ applies +/ to each
+    /// group.
+    fn mo_through(
+        &self,
+        cos: &[CN],
+        rhs: &NN,
+        mo: &dyn Fn(&NN) -> VmRes,
+        dy: &dyn Fn(&NN, &NN) -> VmRes,
+    ) -> VmRes {
+        match cos.split_last() {
+            None => mo(rhs),
+            Some((co, inner)) => apply_mo(
+                Some(*co),
+                rhs,
+                &|a| self.mo_through(inner, a, mo, dy),
+                &|w, a| self.do_through(inner, w, a, mo, dy),
+            ),
+        }
+    }
+
+    /// Apply a function dyadically through its combinators.
+    fn do_through(
+        &self,
+        cos: &[CN],
+        lhs: &NN,
+        rhs: &NN,
+        mo: &dyn Fn(&NN) -> VmRes,
+        dy: &dyn Fn(&NN, &NN) -> VmRes,
+    ) -> VmRes {
+        let Some((co, inner)) = cos.split_last() else {
+            return dy(lhs, rhs);
+        };
+        let derived_mo = |a: &NN| self.mo_through(inner, a, mo, dy);
+        let derived_dy = |w: &NN, a: &NN| self.do_through(inner, w, a, mo, dy);
         match co {
-            None => {
-                debug_println!("cdo None");
-                let lhs = self.pop();
-                let rhs = self.pop();
-                debug_println!("cdo lhs: {}", lhs);
-                debug_println!("cdo rhs: {}", rhs);
-
-                let (_, fun) = Self::get_fun(fun);
-                let result = fun(&lhs, &rhs)?;
-                self.push(result);
-            }
-            Some(CN::ScanL) => {
-                debug_println!("cdo ScanL");
-                let lhs = self.pop();
-                let rhs = self.pop();
-                debug_println!("cdo rhs: {}", rhs);
-                debug_println!("cdo lhs: {}", lhs);
-
-                let (_, fun) = Self::get_fun(fun);
-
-                match lhs.n {
-                    E::LIST(l) => {
-                        let results: Result<Vec<NN>, VMError> = l
-                            .into_iter()
-                            .map(|w| match rhs.n.clone() {
-                                E::LIST(r) => {
-                                    let inner: Result<Vec<NN>, VMError> =
-                                        r.into_iter().map(|a| fun(&w.clone(), &a)).collect();
-                                    Ok(NN::nd(E::LIST(inner?)))
-                                }
-                                E::INT(_) => fun(&w, &rhs.clone()),
-                                _ => Err(VMError::new(format!(
-                                    "scan-left (\\) rhs: expected list or int, got {}",
-                                    type_name(&rhs)
-                                ))),
-                            })
-                            .collect();
-                        self.push(NN::nd(E::LIST(results?)));
-                    }
-                    _ => {
-                        return Err(VMError::new(format!(
-                            "scan-left (\\) lhs: expected list, got {}",
-                            type_name(&lhs)
-                        )));
-                    }
-                }
-            }
-            Some(other) => {
-                return Err(VMError::new(format!(
-                    "combinator {} not supported in dyadic context",
-                    other
-                )));
-            }
+            // These apply the function monadically, with a dyadic call
+            CN::Power => self.power(lhs, rhs, &derived_mo),
+            CN::Key => key(lhs, rhs, &derived_mo),
+            _ => apply_do(Some(*co), lhs, rhs, &derived_dy),
         }
+    }
+
+    pub fn cmo(&mut self, cos: &[CN], fun: FN, _ip: usize) -> Result<(), VMError> {
+        let rhs = self.pop();
+        debug_println!("cmo: rhs: {}", rhs);
+        let (mo, dy) = Self::get_fun(fun);
+        let result = self.mo_through(cos, &rhs, &mo, &dy)?;
+        self.push(result);
+        Ok(())
+    }
+
+    pub fn cdo(&mut self, cos: &[CN], fun: FN, _ip: usize) -> Result<(), VMError> {
+        let lhs = self.pop();
+        let rhs = self.pop();
+        debug_println!("cdo lhs: {}", lhs);
+        debug_println!("cdo rhs: {}", rhs);
+        let (mo, dy) = Self::get_fun(fun);
+        let result = self.do_through(cos, &lhs, &rhs, &mo, &dy)?;
+        self.push(result);
         Ok(())
     }
 
     pub fn get_fun(fun: FN) -> (MonadicFn, DyadicFn) {
         match fun {
             FN::Bang => (mo_bang, do_mathmod),
-            FN::Eq => (mo_eq, do_noimpl),
-            FN::Div => (mo_noimpl, do_noimpl),
+            FN::Eq => (mo_eq, do_eq),
+            FN::Lt => (mo_noimpl, do_lt),
+            FN::Gt => (mo_noimpl, do_gt),
+            FN::Le => (mo_noimpl, do_le),
+            FN::Ge => (mo_noimpl, do_ge),
+            FN::Ne => (mo_noimpl, do_ne),
+            FN::Div => (mo_div, do_mathdiv),
             FN::Max => (mo_noimpl, do_max),
             FN::Min => (mo_min, do_min),
-            FN::Amp => (mo_noimpl, do_amp),
+            FN::Amp => (mo_where, do_amp),
             FN::Plus => (mo_noimpl, do_plus),
             FN::Minus => (mo_minus, do_minus),
-            FN::Mult => (mo_noimpl, do_noimpl),
+            FN::Mult => (mo_mult, do_mult),
             FN::Rho => (mo_rho, do_rho),
+            FN::At => (mo_noimpl, do_at),
+            FN::GradeUp => (mo_gradeup, do_noimpl),
+            FN::GradeDown => (mo_gradedown, do_noimpl),
+            FN::SortUp => (mo_sortup, do_noimpl),
+            FN::SortDown => (mo_sortdown, do_noimpl),
+            FN::Reverse => (mo_reverse, do_rotate),
+            FN::Transpose => (mo_transpose, do_transpose),
+            FN::Take => (mo_noimpl, do_take),
+            FN::Drop => (mo_noimpl, do_drop),
+            FN::Cat => (mo_ravel, do_cat),
+            FN::CatFirst => (mo_noimpl, do_cat_first),
+            FN::IndexOf => (mo_noimpl, do_index_of),
+            FN::Member => (mo_noimpl, do_member),
+            FN::Union => (mo_unique, do_union),
+            FN::Intersect => (mo_noimpl, do_intersect),
+            FN::Without => (mo_noimpl, do_without),
+            FN::Group => (mo_group, do_group),
         }
     }
 
     pub fn get_usize(&mut self, ip: usize) -> usize {
-        return u8_to_u(self.b.op[ip + 1], self.b.op[ip + 2]);
+        u8_to_u(self.b.op[ip + 1], self.b.op[ip + 2])
     }
 
     pub fn push(&mut self, node: NN) {
@@ -608,7 +874,7 @@ impl V {
     }
 
     pub fn cpop(&mut self) -> C {
-        let node = self.context[self.cptr - 1].clone();
+        let node = self.context[self.cptr - 1];
         self.cptr -= 1;
         node
     }
@@ -633,6 +899,297 @@ impl V {
     }
 }
 
+// ---------------------------------------------------------------------------
+// User functions
+// ---------------------------------------------------------------------------
+
+/// A user function resolved by name, ready to call.
+struct UserFn<'a> {
+    name: String,
+    val: &'a NN,
+    nparams: usize,
+    root: &'a B,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl UserFn<'_> {
+    fn call(&self, args: Vec<NN>) -> VmRes {
+        self.check_arity(args.len())?;
+        V::run_ufnv(self, args)
+    }
+
+    fn check_arity(&self, nargs: usize) -> Result<(), VMError> {
+        match nargs == self.nparams {
+            true => Ok(()),
+            false => Err(VMError::new(format!(
+                "function {} expects {} args, got {}",
+                self.name, self.nparams, nargs
+            ))),
+        }
+    }
+}
+
+/// A call in tail position, waiting for its caller's frame to be dropped.
+struct TailCall {
+    name: String,
+    val: NN,
+    args: Vec<NN>,
+}
+
+// ---------------------------------------------------------------------------
+// Combinators
+// ---------------------------------------------------------------------------
+
+/// Apply a function monadically, through a combinator if there is one.
+/// `mo` and `dy` are the monadic and dyadic forms of the function.
+fn apply_mo(
+    co: Option<CN>,
+    rhs: &NN,
+    mo: &dyn Fn(&NN) -> VmRes,
+    dy: &dyn Fn(&NN, &NN) -> VmRes,
+) -> VmRes {
+    match co {
+        None => mo(rhs),
+        Some(CN::Fold) => fold(rhs, dy),
+        Some(CN::Scan) => scan(rhs, dy),
+        Some(CN::Each) => each_mo(rhs, mo),
+        Some(CN::Key) => key_mo(rhs, mo),
+        Some(other) => Err(VMError::new(format!(
+            "combinator {} not supported in monadic context",
+            other
+        ))),
+    }
+}
+
+/// Apply a function dyadically, through a combinator if there is one.
+fn apply_do(co: Option<CN>, lhs: &NN, rhs: &NN, dy: &dyn Fn(&NN, &NN) -> VmRes) -> VmRes {
+    match co {
+        None => dy(lhs, rhs),
+        Some(CN::EachLeft) => each_left(lhs, rhs, dy),
+        Some(CN::Outer) => outer(lhs, rhs, dy),
+        Some(CN::Each) => each_do(lhs, rhs, dy),
+        Some(other) => Err(VMError::new(format!(
+            "combinator {} not supported in dyadic context",
+            other
+        ))),
+    }
+}
+
+/// Key (⌸), monadic: apply a function to the index list of each group
+/// of keys, as grouped by ⊔.
+fn key_mo(rhs: &NN, mo: &dyn Fn(&NN) -> VmRes) -> VmRes {
+    let results = groups("⌸", rhs, None)?
+        .iter()
+        .map(mo)
+        .collect::<Result<_, _>>()?;
+    Ok(NN::nd(E::LIST(results)))
+}
+
+/// Key (⌸), dyadic: apply a function to the values of each group of keys.
+/// Results come in the order of the keys' first occurrence, as in ∪.
+fn key(lhs: &NN, rhs: &NN, mo: &dyn Fn(&NN) -> VmRes) -> VmRes {
+    let results = groups("⌸", lhs, Some(rhs))?
+        .iter()
+        .map(mo)
+        .collect::<Result<_, _>>()?;
+    Ok(NN::nd(E::LIST(results)))
+}
+
+/// Fold (/): reduce a list left to right.
+fn fold(rhs: &NN, dy: &dyn Fn(&NN, &NN) -> VmRes) -> VmRes {
+    match &rhs.n {
+        E::LIST(l) => {
+            let mut iter = l.iter();
+            let first = iter
+                .next()
+                .ok_or_else(|| VMError::new("fold on empty list"))?;
+            iter.try_fold(first.clone(), |acc, a| dy(&acc, a))
+        }
+        _ => Err(VMError::new(format!(
+            "fold (/) expects a list, got {}",
+            type_name(rhs)
+        ))),
+    }
+}
+
+/// Scan (\): fold a list, keeping every intermediate result.
+fn scan(rhs: &NN, dy: &dyn Fn(&NN, &NN) -> VmRes) -> VmRes {
+    match &rhs.n {
+        E::LIST(l) => {
+            let mut results: Vec<NN> = Vec::with_capacity(l.len());
+            for a in l {
+                let next = match results.last() {
+                    Some(acc) => dy(acc, a)?,
+                    None => a.clone(),
+                };
+                results.push(next);
+            }
+            Ok(NN::nd(E::LIST(results)))
+        }
+        _ => Err(VMError::new(format!(
+            "scan (\\) expects a list, got {}",
+            type_name(rhs)
+        ))),
+    }
+}
+
+/// Each-left (⊣): apply each item of lhs against the whole of rhs.
+fn each_left(lhs: &NN, rhs: &NN, dy: &dyn Fn(&NN, &NN) -> VmRes) -> VmRes {
+    match &lhs.n {
+        E::LIST(l) => {
+            let results: Result<Vec<NN>, VMError> = l
+                .iter()
+                .map(|w| match &rhs.n {
+                    E::LIST(r) => {
+                        let inner: Result<Vec<NN>, VMError> = r.iter().map(|a| dy(w, a)).collect();
+                        Ok(NN::nd(E::LIST(inner?)))
+                    }
+                    E::INT(_) => dy(w, rhs),
+                    _ => Err(VMError::new(format!(
+                        "each-left (⊣) rhs: expected list or int, got {}",
+                        type_name(rhs)
+                    ))),
+                })
+                .collect();
+            Ok(NN::nd(E::LIST(results?)))
+        }
+        _ => Err(VMError::new(format!(
+            "each-left (⊣) lhs: expected list, got {}",
+            type_name(lhs)
+        ))),
+    }
+}
+
+/// Outer product (∘): apply the function to every pairing of an item of
+/// lhs with an item of rhs. The result has the rank of both sides combined.
+fn outer(lhs: &NN, rhs: &NN, dy: &dyn Fn(&NN, &NN) -> VmRes) -> VmRes {
+    match (&lhs.n, &rhs.n) {
+        (E::LIST(l), _) => {
+            let rows: Result<Vec<NN>, VMError> = l.iter().map(|w| outer(w, rhs, dy)).collect();
+            Ok(NN::nd(E::LIST(rows?)))
+        }
+        (_, E::LIST(r)) => {
+            let cols: Result<Vec<NN>, VMError> = r.iter().map(|a| outer(lhs, a, dy)).collect();
+            Ok(NN::nd(E::LIST(cols?)))
+        }
+        _ => dy(lhs, rhs),
+    }
+}
+
+/// Inner product (f.g): combine matching items with g and reduce with f,
+/// pairing rows of lhs with columns of rhs. A vector on either side stands
+/// for a single row or column, and that axis is dropped from the result.
+fn inner(
+    lhs: &NN,
+    rhs: &NN,
+    r: &dyn Fn(&NN, &NN) -> VmRes,
+    c: &dyn Fn(&NN, &NN) -> VmRes,
+) -> VmRes {
+    let (rows, lvec) = matrix_rows(lhs)?;
+    let (rrows, rvec) = matrix_rows(rhs)?;
+    let inner_len = match rvec {
+        true => rrows[0].len(),
+        false => rrows.len(),
+    };
+    let cols = match rvec {
+        true => rrows,
+        false => (0..rrows[0].len())
+            .map(|j| rrows.iter().map(|row| row[j].clone()).collect())
+            .collect(),
+    };
+
+    let dot = |u: &Vec<NN>, v: &Vec<NN>| -> VmRes {
+        if u.len() != inner_len {
+            return Err(VMError::new(format!(
+                "inner product length mismatch: {} and {}",
+                u.len(),
+                inner_len
+            )));
+        }
+        let mut pairs = u.iter().zip(v.iter());
+        let (w, a) = pairs
+            .next()
+            .ok_or_else(|| VMError::new("inner product on empty lists"))?;
+        pairs.try_fold(c(w, a)?, |acc, (w, a)| r(&acc, &c(w, a)?))
+    };
+
+    let table: Result<Vec<NN>, VMError> = rows
+        .iter()
+        .map(|u| {
+            let row: Result<Vec<NN>, VMError> = cols.iter().map(|v| dot(u, v)).collect();
+            let row = row?;
+            Ok(match rvec {
+                true => row[0].clone(),
+                false => NN::nd(E::LIST(row)),
+            })
+        })
+        .collect();
+    let mut table = table?;
+    match lvec {
+        true => Ok(table.remove(0)),
+        false => Ok(NN::nd(E::LIST(table))),
+    }
+}
+
+/// Split a vector or matrix into rows, flagging a vector as a single row.
+/// A matrix must be rectangular.
+fn matrix_rows(n: &NN) -> Result<(Vec<Vec<NN>>, bool), VMError> {
+    match &n.n {
+        E::LIST(l) if !l.is_empty() && l.iter().all(|el| matches!(el.n, E::LIST(_))) => {
+            let rows: Vec<Vec<NN>> = l
+                .iter()
+                .map(|el| match &el.n {
+                    E::LIST(row) => row.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            match rows.iter().all(|row| row.len() == rows[0].len()) {
+                true => Ok((rows, false)),
+                false => Err(VMError::new("inner product expects a rectangular matrix")),
+            }
+        }
+        E::LIST(l) => Ok((vec![l.clone()], true)),
+        _ => Err(VMError::new(format!(
+            "inner product expects a vector or matrix, got {}",
+            type_name(n)
+        ))),
+    }
+}
+
+/// Each (ǁ), monadic: apply the function to every item of a list.
+/// An atom is passed through as a single item.
+fn each_mo(rhs: &NN, mo: &dyn Fn(&NN) -> VmRes) -> VmRes {
+    match &rhs.n {
+        E::LIST(l) => {
+            let results: Result<Vec<NN>, VMError> = l.iter().map(mo).collect();
+            Ok(NN::nd(E::LIST(results?)))
+        }
+        _ => mo(rhs),
+    }
+}
+
+/// Each (ǁ), dyadic: pair up items of two equal-length lists. An atom on
+/// either side is paired with every item of the other.
+fn each_do(lhs: &NN, rhs: &NN, dy: &dyn Fn(&NN, &NN) -> VmRes) -> VmRes {
+    let results: Result<Vec<NN>, VMError> = match (&lhs.n, &rhs.n) {
+        (E::LIST(l), E::LIST(r)) => {
+            if l.len() != r.len() {
+                return Err(VMError::new(format!(
+                    "each (ǁ) list length mismatch: {} vs {}",
+                    l.len(),
+                    r.len()
+                )));
+            }
+            l.iter().zip(r).map(|(w, a)| dy(w, a)).collect()
+        }
+        (E::LIST(l), _) => l.iter().map(|w| dy(w, rhs)).collect(),
+        (_, E::LIST(r)) => r.iter().map(|a| dy(lhs, a)).collect(),
+        _ => return dy(lhs, rhs),
+    };
+    Ok(NN::nd(E::LIST(results?)))
+}
+
 // ---------------------------------------------------------------------------
 // Placeholder for unimplemented monadic / dyadic ops
 // ---------------------------------------------------------------------------
@@ -670,8 +1227,12 @@ pub fn mo_bang(rhs: &NN) -> VmRes {
 
 pub fn mo_minus(rhs: &NN) -> VmRes {
     match rhs.n {
-        E::INT(i) => Ok(NN::nd(E::INT(-i))),
+        E::INT(i) => Ok(NN::nd(
+            i.checked_neg().map_or_else(|| E::FT(-(i as f64)), E::INT),
+        )),
+        E::BOOL(b) => Ok(NN::nd(E::INT(-bool_to_int(b)))),
         E::FT(f) => Ok(NN::nd(E::FT(-f))),
+        E::LIST(_) => mo_conversion(rhs, mo_minus),
         _ => Err(VMError::new(format!(
             "- (negate) expects int or float, got {}",
             type_name(rhs)
@@ -679,6 +1240,33 @@ pub fn mo_minus(rhs: &NN) -> VmRes {
     }
 }
 
+pub fn mo_mult(rhs: &NN) -> VmRes {
+    match rhs.n {
+        E::INT(i) => Ok(NN::nd(E::INT(i.signum()))),
+        E::BOOL(b) => Ok(NN::nd(E::INT(bool_to_int(b)))),
+        E::FT(0.0) => Ok(NN::nd(E::INT(0))),
+        E::FT(f) => Ok(NN::nd(E::INT(f.signum() as i32))),
+        E::LIST(_) => mo_conversion(rhs, mo_mult),
+        _ => Err(VMError::new(format!(
+            "× (sign) expects int, float or list, got {}",
+            type_name(rhs)
+        ))),
+    }
+}
+
+/// Monadic ÷: reciprocal, with the same zero policy as dyadic ÷.
+pub fn mo_div(rhs: &NN) -> VmRes {
+    match (&rhs.n, as_f64(rhs)) {
+        (E::LIST(_), _) => mo_conversion(rhs, mo_div),
+        (_, Some(0.0)) => Err(VMError::new("division by zero")),
+        (_, Some(a)) => Ok(NN::nd(E::FT(1.0 / a))),
+        (_, None) => Err(VMError::new(format!(
+            "÷ (reciprocal) expects int, float or list, got {}",
+            type_name(rhs)
+        ))),
+    }
+}
+
 pub fn mo_eq(rhs: &NN) -> VmRes {
     match rhs.clone().n {
         E::INT(i) => Ok(NN::nd(E::BOOL(i == 0))),
@@ -703,41 +1291,119 @@ pub fn mo_eq(rhs: &NN) -> VmRes {
     }
 }
 
+/// Monadic &: where. The index of each item, repeated by its count, so a
+/// boolean mask gives the indices where it is true.
+pub fn mo_where(rhs: &NN) -> VmRes {
+    match &rhs.n {
+        E::LIST(l) => {
+            let mut out = Vec::new();
+            for (i, w) in l.iter().enumerate() {
+                let n = amp_count(w, "where")?;
+                out.extend(std::iter::repeat_n(NN::nd(E::INT(i as i32)), n));
+            }
+            Ok(NN::nd(E::LIST(out)))
+        }
+        _ => Err(VMError::new(format!(
+            "& (where) expects a list, got {}",
+            type_name(rhs)
+        ))),
+    }
+}
+
 pub fn mo_min(rhs: &NN) -> VmRes {
-    match rhs.clone().n {
+    match rhs.n {
         E::FT(i) => Ok(NN::nd(E::INT(i.floor() as i32))),
+        E::INT(i) => Ok(NN::nd(E::INT(i))),
+        E::BOOL(b) => Ok(NN::nd(E::INT(bool_to_int(b)))),
+        E::LIST(_) => mo_conversion(rhs, mo_min),
         _ => Err(VMError::new(format!(
-            "_ (floor) expects float, got {}",
+            "_ (floor) expects int or float, got {}",
             type_name(rhs)
         ))),
     }
 }
 
-// ---------------------------------------------------------------------------
-// Dyadic helpers
-// ---------------------------------------------------------------------------
-
-pub fn bool_to_int(b: bool) -> i32 {
+/// Pervade a scalar monadic function through a list of any depth.
+pub fn mo_conversion(rhs: &NN, mo_target: MonadicFn) -> VmRes {
+    match &rhs.n {
+        E::LIST(l) => {
+            let results: Result<Vec<NN>, VMError> = l.par_iter().map(mo_target).collect();
+            Ok(NN::nd(E::LIST(results?)))
+        }
+        _ => mo_target(rhs),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Dyadic helpers
+// ---------------------------------------------------------------------------
+
+pub fn bool_to_int(b: bool) -> i32 {
     match b {
         true => 1,
         false => 0,
     }
 }
 
+/// Two numeric scalars promoted to a common type on the numeric tower
+/// bool < int < float.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Num {
+    I(i32, i32),
+    F(f64, f64),
+}
+
+/// Promote a pair of numeric scalars up the tower until they match.
+/// None if either side is not a numeric scalar.
+pub fn promote(lhs: &NN, rhs: &NN) -> Option<Num> {
+    let as_i32 = |n: &NN| match n.n {
+        E::INT(i) => Some(i),
+        E::BOOL(b) => Some(bool_to_int(b)),
+        _ => None,
+    };
+    match (as_i32(lhs), as_i32(rhs)) {
+        (Some(w), Some(a)) => Some(Num::I(w, a)),
+        _ => Some(Num::F(as_f64(lhs)?, as_f64(rhs)?)),
+    }
+}
+
+/// Read a numeric scalar as a float, or None for anything non-numeric.
+pub fn as_f64(n: &NN) -> Option<f64> {
+    match n.n {
+        E::INT(i) => Some(i as f64),
+        E::FT(f) => Some(f),
+        E::BOOL(b) => Some(bool_to_int(b) as f64),
+        _ => None,
+    }
+}
+
+/// Pervade a scalar dyadic function through its arguments. Lists pair up
+/// item by item at every depth; an atom on either side extends to match
+/// a list of any depth on the other.
 pub fn do_conversion(lhs: &NN, rhs: &NN, do_target: DyadicFn) -> VmRes {
-    match (lhs.clone().n, rhs.clone().n) {
-        (E::INT(_), E::INT(_)) => do_target(lhs, rhs),
-        (E::BOOL(_), E::BOOL(_)) => do_target(lhs, rhs),
+    match (&lhs.n, &rhs.n) {
         (E::LIST(l), E::LIST(r)) => {
-            if l.len() != r.len() {
+            let (ls, rs) = (shape_of(lhs), shape_of(rhs));
+            let rank = ls.len().min(rs.len());
+            if l.len() != r.len() || ls[..rank] != rs[..rank] {
                 return Err(VMError::new(format!(
-                    "list length mismatch: {} vs {}",
-                    l.len(),
-                    r.len()
+                    "shape mismatch: {} vs {}",
+                    fmt_shape(&ls),
+                    fmt_shape(&rs)
                 )));
             }
             let results: Result<Vec<NN>, VMError> =
-                l.par_iter().zip(r).map(|(l, r)| do_target(l, &r)).collect();
+                l.par_iter().zip(r).map(|(l, r)| do_target(l, r)).collect();
+            Ok(NN::nd(E::LIST(results?)))
+        }
+        (E::LIST(l), _) => {
+            let results: Result<Vec<NN>, VMError> =
+                l.par_iter().map(|w| do_target(w, rhs)).collect();
+            Ok(NN::nd(E::LIST(results?)))
+        }
+        (_, E::LIST(r)) => {
+            let results: Result<Vec<NN>, VMError> =
+                r.par_iter().map(|a| do_target(lhs, a)).collect();
             Ok(NN::nd(E::LIST(results?)))
         }
         _ => Err(VMError::new(format!(
@@ -748,93 +1414,775 @@ pub fn do_conversion(lhs: &NN, rhs: &NN, do_target: DyadicFn) -> VmRes {
     }
 }
 
+/// Shape of a (possibly nested) list, read down its first items.
+/// An atom has the empty shape.
+pub fn shape_of(n: &NN) -> Vec<usize> {
+    let mut shape = vec![];
+    let mut cur = n;
+    while let E::LIST(l) = &cur.n {
+        shape.push(l.len());
+        match l.first() {
+            Some(first) => cur = first,
+            None => break,
+        }
+    }
+    shape
+}
+
+fn fmt_shape(shape: &[usize]) -> String {
+    let dims: Vec<String> = shape.iter().map(|d| d.to_string()).collect();
+    dims.join("×")
+}
+
 // ---------------------------------------------------------------------------
 // Dyadic built-ins
 // ---------------------------------------------------------------------------
 
+/// The only int remainder that overflows, the most negative int by -1, is 0.
 pub fn do_mathmod(lhs: &NN, rhs: &NN) -> VmRes {
-    match (&lhs.n, &rhs.n) {
-        (E::INT(w), E::INT(a)) => {
-            if *w == 0 {
-                return Err(VMError::new("modulo by zero"));
-            }
-            Ok(NN::nd(E::INT(a % w)))
-        }
-        _ => do_conversion(lhs, rhs, do_mathmod),
+    match promote(lhs, rhs) {
+        Some(Num::I(0, _)) => Err(VMError::new("modulo by zero")),
+        Some(Num::I(w, a)) => Ok(NN::nd(E::INT(a.checked_rem(w).unwrap_or(0)))),
+        Some(Num::F(0.0, _)) => Err(VMError::new("modulo by zero")),
+        Some(Num::F(w, a)) => Ok(NN::nd(E::FT(a % w))),
+        None => do_conversion(lhs, rhs, do_mathmod),
     }
 }
 
+/// Dyadic ÷: lhs divided by rhs. The result is always a float, and dividing
+/// by zero is an error rather than ∞.
 pub fn do_mathdiv(lhs: &NN, rhs: &NN) -> VmRes {
-    match (&lhs.n, &rhs.n) {
-        (E::INT(w), E::INT(a)) => {
-            if *w == 0 {
-                return Err(VMError::new("division by zero"));
-            }
-            Ok(NN::nd(E::FT((*a as f64) / (*w as f64))))
-        }
+    match (as_f64(lhs), as_f64(rhs)) {
+        (Some(_), Some(0.0)) => Err(VMError::new("division by zero")),
+        (Some(w), Some(a)) => Ok(NN::nd(E::FT(w / a))),
         _ => do_conversion(lhs, rhs, do_mathdiv),
     }
 }
 
 pub fn do_max(lhs: &NN, rhs: &NN) -> VmRes {
     match (&lhs.n, &rhs.n) {
-        (E::INT(w), E::INT(a)) => Ok(NN::nd(E::INT(cmp::max(*a, *w)))),
         (E::BOOL(w), E::BOOL(a)) => Ok(NN::nd(E::BOOL(*w || *a))),
-        _ => do_conversion(lhs, rhs, do_max),
+        _ => match promote(lhs, rhs) {
+            Some(Num::I(w, a)) => Ok(NN::nd(E::INT(cmp::max(w, a)))),
+            Some(Num::F(w, a)) => Ok(NN::nd(E::FT(w.max(a)))),
+            None => do_conversion(lhs, rhs, do_max),
+        },
     }
 }
 
 pub fn do_min(lhs: &NN, rhs: &NN) -> VmRes {
     match (&lhs.n, &rhs.n) {
-        (E::INT(w), E::INT(a)) => Ok(NN::nd(E::INT(cmp::min(*a, *w)))),
-        _ => do_conversion(lhs, rhs, do_min),
+        (E::BOOL(w), E::BOOL(a)) => Ok(NN::nd(E::BOOL(*w && *a))),
+        _ => match promote(lhs, rhs) {
+            Some(Num::I(w, a)) => Ok(NN::nd(E::INT(cmp::min(w, a)))),
+            Some(Num::F(w, a)) => Ok(NN::nd(E::FT(w.min(a)))),
+            None => do_conversion(lhs, rhs, do_min),
+        },
     }
 }
 
+/// How many times & repeats an item: a bool is 0 or 1, an int its value.
+fn amp_count(n: &NN, what: &str) -> Result<usize, VMError> {
+    match n.n {
+        E::BOOL(b) => Ok(bool_to_int(b) as usize),
+        E::INT(i) if i >= 0 => Ok(i as usize),
+        E::INT(i) => Err(VMError::new(format!(
+            "& ({}) count must not be negative, got {}",
+            what, i
+        ))),
+        _ => Err(VMError::new(format!(
+            "& ({}) expects bool or int counts, got {}",
+            what,
+            type_name(n)
+        ))),
+    }
+}
+
+/// Dyadic &: replicate each item of rhs by the matching count in lhs.
+/// A boolean mask keeps the items where it is true.
 pub fn do_amp(lhs: &NN, rhs: &NN) -> VmRes {
-    match (lhs.clone().n, rhs.clone().n) {
+    match (&lhs.n, &rhs.n) {
         (E::LIST(l), E::LIST(a)) => {
             if l.len() != a.len() {
                 return Err(VMError::new(format!(
-                    "& (filter) list length mismatch: {} vs {}",
+                    "& (replicate) list length mismatch: {} vs {}",
                     l.len(),
                     a.len()
                 )));
             }
-            Ok(NN::nd(E::LIST(l.into_iter().enumerate().fold(
-                Vec::new(),
-                |mut r: Vec<NN>, (i, w)| match w.n {
-                    E::BOOL(true) => {
-                        r.push(a[i].clone());
-                        r
-                    }
-                    _ => r,
-                },
-            ))))
+            let mut out = Vec::new();
+            for (w, item) in l.iter().zip(a) {
+                let n = amp_count(w, "replicate")?;
+                out.extend(std::iter::repeat_n(item, n).cloned());
+            }
+            Ok(NN::nd(E::LIST(out)))
         }
         _ => Err(VMError::new(format!(
-            "& (filter) expects two lists, got {} and {}",
+            "& (replicate) expects two lists, got {} and {}",
             type_name(lhs),
             type_name(rhs)
         ))),
     }
 }
 
+/// An int sum too big for an int promotes to float.
 pub fn do_plus(lhs: &NN, rhs: &NN) -> VmRes {
-    match (&lhs.n, &rhs.n) {
-        (E::INT(w), E::INT(a)) => Ok(NN::nd(E::INT(w + a))),
-        (E::BOOL(w), E::BOOL(a)) => Ok(NN::nd(E::INT(bool_to_int(*w) + bool_to_int(*a)))),
-        _ => do_conversion(lhs, rhs, do_plus),
+    match promote(lhs, rhs) {
+        Some(Num::I(w, a)) => Ok(NN::nd(
+            w.checked_add(a)
+                .map_or_else(|| E::FT(w as f64 + a as f64), E::INT),
+        )),
+        Some(Num::F(w, a)) => Ok(NN::nd(E::FT(w + a))),
+        None => do_conversion(lhs, rhs, do_plus),
     }
 }
 
+/// An int difference too big for an int promotes to float.
 pub fn do_minus(lhs: &NN, rhs: &NN) -> VmRes {
+    match promote(lhs, rhs) {
+        Some(Num::I(w, a)) => Ok(NN::nd(
+            w.checked_sub(a)
+                .map_or_else(|| E::FT(w as f64 - a as f64), E::INT),
+        )),
+        Some(Num::F(w, a)) => Ok(NN::nd(E::FT(w - a))),
+        None => do_conversion(lhs, rhs, do_minus),
+    }
+}
+
+/// An int product too big for an int promotes to float.
+pub fn do_mult(lhs: &NN, rhs: &NN) -> VmRes {
+    match promote(lhs, rhs) {
+        Some(Num::I(w, a)) => Ok(NN::nd(
+            w.checked_mul(a)
+                .map_or_else(|| E::FT(w as f64 * a as f64), E::INT),
+        )),
+        Some(Num::F(w, a)) => Ok(NN::nd(E::FT(w * a))),
+        None => do_conversion(lhs, rhs, do_mult),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Comparisons
+// ---------------------------------------------------------------------------
+
+/// Order two atoms: numbers after promotion, strings lexicographically.
+/// None when the pair has no ordering (mixed kinds, NaN).
+pub fn compare_atoms(lhs: &NN, rhs: &NN) -> Option<Ordering> {
+    match (promote(lhs, rhs), &lhs.n, &rhs.n) {
+        (Some(Num::I(w, a)), _, _) => Some(w.cmp(&a)),
+        (Some(Num::F(w, a)), _, _) => w.partial_cmp(&a),
+        (None, E::ST(w), E::ST(a)) => Some(w.cmp(a)),
+        _ => None,
+    }
+}
+
+/// Shared body of the ordering comparisons: pervade through lists, then
+/// turn the ordering of two atoms into a boolean.
+fn compare(lhs: &NN, rhs: &NN, sym: &str, test: fn(Ordering) -> bool, dy: DyadicFn) -> VmRes {
+    match (&lhs.n, &rhs.n) {
+        (E::LIST(_), _) | (_, E::LIST(_)) => do_conversion(lhs, rhs, dy),
+        _ => match compare_atoms(lhs, rhs) {
+            Some(o) => Ok(NN::nd(E::BOOL(test(o)))),
+            None => Err(VMError::new(format!(
+                "{} cannot compare {} and {}",
+                sym,
+                type_name(lhs),
+                type_name(rhs)
+            ))),
+        },
+    }
+}
+
+/// Dyadic =: atoms of different kinds are simply unequal.
+pub fn do_eq(lhs: &NN, rhs: &NN) -> VmRes {
+    match (&lhs.n, &rhs.n) {
+        (E::LIST(_), _) | (_, E::LIST(_)) => do_conversion(lhs, rhs, do_eq),
+        _ => Ok(NN::nd(E::BOOL(
+            compare_atoms(lhs, rhs) == Some(Ordering::Equal),
+        ))),
+    }
+}
+
+pub fn do_ne(lhs: &NN, rhs: &NN) -> VmRes {
+    match (&lhs.n, &rhs.n) {
+        (E::LIST(_), _) | (_, E::LIST(_)) => do_conversion(lhs, rhs, do_ne),
+        _ => Ok(NN::nd(E::BOOL(
+            compare_atoms(lhs, rhs) != Some(Ordering::Equal),
+        ))),
+    }
+}
+
+pub fn do_lt(lhs: &NN, rhs: &NN) -> VmRes {
+    compare(lhs, rhs, "<", Ordering::is_lt, do_lt)
+}
+
+pub fn do_gt(lhs: &NN, rhs: &NN) -> VmRes {
+    compare(lhs, rhs, ">", Ordering::is_gt, do_gt)
+}
+
+pub fn do_le(lhs: &NN, rhs: &NN) -> VmRes {
+    compare(lhs, rhs, "≤", Ordering::is_le, do_le)
+}
+
+pub fn do_ge(lhs: &NN, rhs: &NN) -> VmRes {
+    compare(lhs, rhs, "≥", Ordering::is_ge, do_ge)
+}
+
+// ---------------------------------------------------------------------------
+// Grade / Sort (⍋ ⍒ ∧ ∨)
+// ---------------------------------------------------------------------------
+
+/// A total order over values, for sorting. Numbers compare after promotion
+/// (NaN last), strings lexicographically, and lists item by item with a
+/// shorter prefix first. Across kinds: numbers, then strings, then lists.
+pub fn total_cmp(lhs: &NN, rhs: &NN) -> Ordering {
+    let kind = |n: &NN| match n.n {
+        E::INT(_) | E::FT(_) | E::BOOL(_) => 0,
+        E::ST(_) => 1,
+        E::LIST(_) => 2,
+        _ => 3,
+    };
+    match (promote(lhs, rhs), &lhs.n, &rhs.n) {
+        (Some(Num::I(w, a)), _, _) => w.cmp(&a),
+        (Some(Num::F(w, a)), _, _) => w.total_cmp(&a),
+        (None, E::ST(w), E::ST(a)) => w.cmp(a),
+        (None, E::LIST(w), E::LIST(a)) => w
+            .iter()
+            .zip(a)
+            .map(|(w, a)| total_cmp(w, a))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| w.len().cmp(&a.len())),
+        _ => kind(lhs).cmp(&kind(rhs)),
+    }
+}
+
+/// The permutation that sorts a list. The sort is stable, so equal items
+/// keep their original order in either direction.
+fn grade(rhs: &NN, sym: &str, descending: bool) -> Result<Vec<usize>, VMError> {
+    match &rhs.n {
+        E::LIST(l) => {
+            let mut idx: Vec<usize> = (0..l.len()).collect();
+            idx.sort_by(|&i, &j| match descending {
+                true => total_cmp(&l[j], &l[i]),
+                false => total_cmp(&l[i], &l[j]),
+            });
+            Ok(idx)
+        }
+        _ => Err(VMError::new(format!(
+            "{} expects a list, got {}",
+            sym,
+            type_name(rhs)
+        ))),
+    }
+}
+
+fn indices_to_nn(idx: Vec<usize>) -> NN {
+    NN::nd(E::LIST(
+        idx.into_iter().map(|i| NN::nd(E::INT(i as i32))).collect(),
+    ))
+}
+
+/// Sort a list by its grade.
+fn sort(rhs: &NN, sym: &str, descending: bool) -> VmRes {
+    let idx = grade(rhs, sym, descending)?;
+    match &rhs.n {
+        E::LIST(l) => Ok(NN::nd(E::LIST(
+            idx.into_iter().map(|i| l[i].clone()).collect(),
+        ))),
+        _ => unreachable!(),
+    }
+}
+
+/// Monadic ⍋: indices that put the list in ascending order.
+pub fn mo_gradeup(rhs: &NN) -> VmRes {
+    Ok(indices_to_nn(grade(rhs, "⍋", false)?))
+}
+
+/// Monadic ⍒: indices that put the list in descending order.
+pub fn mo_gradedown(rhs: &NN) -> VmRes {
+    Ok(indices_to_nn(grade(rhs, "⍒", true)?))
+}
+
+/// Monadic ∧: sort ascending.
+pub fn mo_sortup(rhs: &NN) -> VmRes {
+    sort(rhs, "∧", false)
+}
+
+/// Monadic ∨: sort descending.
+pub fn mo_sortdown(rhs: &NN) -> VmRes {
+    sort(rhs, "∨", true)
+}
+
+// ---------------------------------------------------------------------------
+// Structure (⌽ ⍉)
+// ---------------------------------------------------------------------------
+
+/// Monadic ⌽: reverse the items of a list. For a matrix that reverses the
+/// order of the rows. An atom is returned unchanged.
+pub fn mo_reverse(rhs: &NN) -> VmRes {
+    match &rhs.n {
+        E::LIST(l) => Ok(NN::nd(E::LIST(l.iter().rev().cloned().collect()))),
+        _ => Ok(rhs.clone()),
+    }
+}
+
+/// Dyadic ⌽: rotate the items of rhs left by lhs places. A negative count
+/// rotates right.
+pub fn do_rotate(lhs: &NN, rhs: &NN) -> VmRes {
+    let n = match lhs.n {
+        E::INT(n) => n,
+        _ => {
+            return Err(VMError::new(format!(
+                "⌽: rotate count must be an int, got {}",
+                type_name(lhs)
+            )))
+        }
+    };
+    match &rhs.n {
+        E::LIST(l) if !l.is_empty() => {
+            let mut items = l.clone();
+            items.rotate_left(n.rem_euclid(l.len() as i32) as usize);
+            Ok(NN::nd(E::LIST(items)))
+        }
+        _ => Ok(rhs.clone()),
+    }
+}
+
+/// Flatten an array of the given shape into its atoms, in row-major order.
+fn flatten_to(n: &NN, shape: &[usize], out: &mut Vec<NN>) -> Result<(), VMError> {
+    match shape.split_first() {
+        None => {
+            out.push(n.clone());
+            Ok(())
+        }
+        Some((&len, rest)) => match &n.n {
+            E::LIST(l) if l.len() == len => l.iter().try_for_each(|el| flatten_to(el, rest, out)),
+            _ => Err(VMError::new("⍉: array must be rectangular")),
+        },
+    }
+}
+
+/// Permute the axes of x: axis k of x becomes axis perm[k] of the result.
+fn transpose(x: &NN, perm: &[usize]) -> VmRes {
+    let shape = shape_of(x);
+    let mut data = Vec::new();
+    flatten_to(x, &shape, &mut data)?;
+
+    let mut new_shape = vec![0; shape.len()];
+    for (k, &p) in perm.iter().enumerate() {
+        new_shape[p] = shape[k];
+    }
+    let mut strides = vec![1; shape.len()];
+    for k in (1..shape.len()).rev() {
+        strides[k - 1] = strides[k] * shape[k];
+    }
+
+    // Walk the result in row-major order, reading each item from x
+    let mut idx = vec![0; shape.len()];
+    let mut out = Vec::with_capacity(data.len());
+    for _ in 0..data.len() {
+        let src: usize = (0..shape.len()).map(|k| idx[perm[k]] * strides[k]).sum();
+        out.push(data[src].clone());
+        for d in (0..idx.len()).rev() {
+            idx[d] += 1;
+            if idx[d] < new_shape[d] {
+                break;
+            }
+            idx[d] = 0;
+        }
+    }
+    Ok(build_nested(&new_shape, &out))
+}
+
+/// Monadic ⍉: reverse the order of the axes. A matrix swaps rows and columns.
+pub fn mo_transpose(rhs: &NN) -> VmRes {
+    let rank = shape_of(rhs).len();
+    match rank {
+        0 | 1 => Ok(rhs.clone()),
+        _ => transpose(rhs, &(0..rank).rev().collect::<Vec<usize>>()),
+    }
+}
+
+/// Dyadic ⍉: move axis k of rhs to position lhs[k] of the result.
+pub fn do_transpose(lhs: &NN, rhs: &NN) -> VmRes {
+    let rank = shape_of(rhs).len();
+    let axes: Vec<NN> = match &lhs.n {
+        E::LIST(l) => l.clone(),
+        _ => vec![lhs.clone()],
+    };
+    let perm: Option<Vec<usize>> = axes
+        .iter()
+        .map(|a| match a.n {
+            E::INT(i) => usize::try_from(i).ok().filter(|&i| i < rank),
+            _ => None,
+        })
+        .collect();
+    match perm {
+        Some(p) if p.len() == rank && (0..rank).all(|k| p.contains(&k)) => transpose(rhs, &p),
+        _ => Err(VMError::new(format!(
+            "⍉: axes must be a permutation of 0 to {} for rank {}",
+            rank.saturating_sub(1),
+            rank
+        ))),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Take / Drop (↑ ↓)
+// ---------------------------------------------------------------------------
+
+/// The fill for padding next to a value: zero of the same numeric kind, an
+/// empty string, or a list of the same shape filled in turn.
+fn fill_of(n: &NN) -> NN {
+    match &n.n {
+        E::FT(_) => NN::nd(E::FT(0.0)),
+        E::ST(_) => NN::nd(E::ST(String::new())),
+        E::LIST(l) => NN::nd(E::LIST(l.iter().map(fill_of).collect())),
+        _ => NN::nd(E::INT(0)),
+    }
+}
+
+/// Read take/drop counts: one int per axis, starting with the first.
+fn counts(lhs: &NN, rhs: &NN, sym: &str) -> Result<Vec<i32>, VMError> {
+    let counts: Option<Vec<i32>> = match &lhs.n {
+        E::INT(n) => Some(vec![*n]),
+        E::LIST(l) => l
+            .iter()
+            .map(|c| match c.n {
+                E::INT(n) => Some(n),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    };
+    let counts = counts.ok_or_else(|| {
+        VMError::new(format!(
+            "{}: count must be an int or a list of ints, got {}",
+            sym,
+            type_name(lhs)
+        ))
+    })?;
+    let rank = shape_of(rhs).len().max(1);
+    match counts.len() <= rank {
+        true => Ok(counts),
+        false => Err(VMError::new(format!(
+            "{}: {} counts for rank {}",
+            sym,
+            counts.len(),
+            rank
+        ))),
+    }
+}
+
+/// The items of a list, or an atom as a list of one.
+fn items_of(n: &NN) -> Vec<NN> {
+    match &n.n {
+        E::LIST(l) => l.clone(),
+        _ => vec![n.clone()],
+    }
+}
+
+/// Take along each axis in turn. Past the end of an axis the result is
+/// padded with the fill of its first item.
+fn take_along(x: &NN, counts: &[i32]) -> VmRes {
+    let (&n, rest) = match counts.split_first() {
+        Some(split) => split,
+        None => return Ok(x.clone()),
+    };
+    let items: Vec<NN> = items_of(x)
+        .iter()
+        .map(|item| take_along(item, rest))
+        .collect::<Result<_, _>>()?;
+    let fill = items.first().map(fill_of).unwrap_or(NN::nd(E::INT(0)));
+
+    let want = n.unsigned_abs() as usize;
+    let keep = want.min(items.len());
+    let pad = vec![fill; want - keep];
+    let taken = match n >= 0 {
+        true => [&items[..keep], &pad[..]].concat(),
+        false => [&pad[..], &items[items.len() - keep..]].concat(),
+    };
+    Ok(NN::nd(E::LIST(taken)))
+}
+
+/// Drop along each axis in turn. Dropping more than the length leaves an
+/// empty list.
+fn drop_along(x: &NN, counts: &[i32]) -> VmRes {
+    let (&n, rest) = match counts.split_first() {
+        Some(split) => split,
+        None => return Ok(x.clone()),
+    };
+    let items = items_of(x);
+    let skip = (n.unsigned_abs() as usize).min(items.len());
+    let kept = match n >= 0 {
+        true => &items[skip..],
+        false => &items[..items.len() - skip],
+    };
+    let dropped: Vec<NN> = kept
+        .iter()
+        .map(|item| drop_along(item, rest))
+        .collect::<Result<_, _>>()?;
+    Ok(NN::nd(E::LIST(dropped)))
+}
+
+/// Dyadic ↑: take the first n items, or the last n for a negative count.
+/// A list of counts takes along each axis of a matrix.
+pub fn do_take(lhs: &NN, rhs: &NN) -> VmRes {
+    take_along(rhs, &counts(lhs, rhs, "↑")?)
+}
+
+/// Dyadic ↓: drop the first n items, or the last n for a negative count.
+/// A list of counts drops along each axis of a matrix.
+pub fn do_drop(lhs: &NN, rhs: &NN) -> VmRes {
+    drop_along(rhs, &counts(lhs, rhs, "↓")?)
+}
+
+// ---------------------------------------------------------------------------
+// Ravel / Catenate (, ⍪)
+// ---------------------------------------------------------------------------
+
+fn ravel_into(n: &NN, out: &mut Vec<NN>) {
+    match &n.n {
+        E::LIST(l) => l.iter().for_each(|el| ravel_into(el, out)),
+        _ => out.push(n.clone()),
+    }
+}
+
+/// Monadic ,: flatten an array of any depth into a list of its atoms.
+pub fn mo_ravel(rhs: &NN) -> VmRes {
+    let mut atoms = Vec::new();
+    ravel_into(rhs, &mut atoms);
+    Ok(NN::nd(E::LIST(atoms)))
+}
+
+/// Dyadic ,: join along the last axis. Lists and atoms join end to end;
+/// for matrices each row of lhs is joined with the matching row of rhs,
+/// and a list or atom on one side adds a column.
+pub fn do_cat(lhs: &NN, rhs: &NN) -> VmRes {
+    let (rl, rr) = (shape_of(lhs).len(), shape_of(rhs).len());
+    if rl <= 1 && rr <= 1 {
+        return Ok(NN::nd(E::LIST([items_of(lhs), items_of(rhs)].concat())));
+    }
+
+    // Pair up the rows; an atom goes with every row
+    let rows = match rl >= rr {
+        true => items_of(lhs).len(),
+        false => items_of(rhs).len(),
+    };
+    let spread = |n: &NN| match &n.n {
+        E::LIST(l) if l.len() == rows => Ok(l.clone()),
+        E::LIST(l) => Err(VMError::new(format!(
+            ",: length mismatch: {} and {}",
+            rows,
+            l.len()
+        ))),
+        _ => Ok(vec![n.clone(); rows]),
+    };
+    let joined: Vec<NN> = spread(lhs)?
+        .iter()
+        .zip(spread(rhs)?.iter())
+        .map(|(w, a)| do_cat(w, a))
+        .collect::<Result<_, _>>()?;
+    Ok(NN::nd(E::LIST(joined)))
+}
+
+/// Dyadic ⍪: join along the first axis. For matrices that stacks the rows
+/// of rhs under those of lhs; a list of matching length, or an atom, adds
+/// a single row.
+pub fn do_cat_first(lhs: &NN, rhs: &NN) -> VmRes {
+    let (sl, sr) = (shape_of(lhs), shape_of(rhs));
+    if sl.len() <= 1 && sr.len() <= 1 {
+        return do_cat(lhs, rhs);
+    }
+
+    // A side of lower rank becomes a single row; an atom is spread to the
+    // shape of a row of the other side
+    let as_rows = |n: &NN, s: &[usize], other: &[usize]| -> Result<Vec<NN>, VMError> {
+        let fits = match s.len().cmp(&other.len()) {
+            Ordering::Greater => other.is_empty() || s.len() == other.len() + 1,
+            Ordering::Equal => s[1..] == other[1..],
+            Ordering::Less => s.is_empty() || s == &other[1..],
+        };
+        match (fits, s.len().cmp(&other.len())) {
+            (false, _) => Err(VMError::new(format!(
+                "⍪: shape mismatch: {} and {}",
+                fmt_shape(&sl),
+                fmt_shape(&sr)
+            ))),
+            (true, Ordering::Less) if s.is_empty() => {
+                let cells = other[1..].iter().product();
+                Ok(vec![build_nested(&other[1..], &vec![n.clone(); cells])])
+            }
+            (true, Ordering::Less) => Ok(vec![n.clone()]),
+            (true, _) => Ok(items_of(n)),
+        }
+    };
+    Ok(NN::nd(E::LIST(
+        [as_rows(lhs, &sl, &sr)?, as_rows(rhs, &sr, &sl)?].concat(),
+    )))
+}
+
+// ---------------------------------------------------------------------------
+// Search, sets and grouping (⍳ ∊ ∪ ∩ ~ ⊔)
+// ---------------------------------------------------------------------------
+//
+// Items are matched with the same equality as =, through a hash of each
+// value, so every lookup is constant time. = promotes an int to compare it
+// with a float, so a key stands a whole float in for the int it equals.
+
+/// The value an item is hashed and matched by: equal under = means equal
+/// keys, except that NaN matches NaN so that it can be found.
+struct Key(NN);
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        same_key(&self.0, &other.0)
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+fn same_key(lhs: &NN, rhs: &NN) -> bool {
     match (&lhs.n, &rhs.n) {
-        (E::INT(w), E::INT(a)) => Ok(NN::nd(E::INT(w - a))),
-        _ => do_conversion(lhs, rhs, do_minus),
+        (E::FT(w), E::FT(a)) => w == a || (w.is_nan() && a.is_nan()),
+        (E::LIST(w), E::LIST(a)) => {
+            w.len() == a.len() && w.iter().zip(a).all(|(w, a)| same_key(w, a))
+        }
+        _ => lhs == rhs,
     }
 }
 
+fn key_of(n: &NN) -> Key {
+    Key(canonical(n))
+}
+
+/// An item with every NaN made the same NaN, so all NaNs hash alike.
+fn canonical(n: &NN) -> NN {
+    match &n.n {
+        E::FT(f) if f.is_nan() => NN::nd(E::FT(f64::NAN)),
+        E::FT(f) if f.fract() == 0.0 && (i32::MIN as f64..=i32::MAX as f64).contains(f) => {
+            NN::nd(E::INT(*f as i32))
+        }
+        E::LIST(l) => NN::nd(E::LIST(l.iter().map(canonical).collect())),
+        _ => n.clone(),
+    }
+}
+
+/// The keys of the items of a list, or of an atom.
+fn keys_of(n: &NN) -> Vec<Key> {
+    items_of(n).iter().map(key_of).collect()
+}
+
+/// Map each item to its one-item result, or an atom to a bare result.
+fn per_item(n: &NN, f: impl Fn(&NN) -> NN) -> NN {
+    match &n.n {
+        E::LIST(l) => NN::nd(E::LIST(l.iter().map(f).collect())),
+        _ => f(n),
+    }
+}
+
+/// Dyadic ⍳: the first position in lhs of each item of rhs, or the length
+/// of lhs for an item that is not there.
+pub fn do_index_of(lhs: &NN, rhs: &NN) -> VmRes {
+    let keys = keys_of(lhs);
+    let missing = keys.len();
+    let mut first: HashMap<Key, usize> = HashMap::with_capacity(missing);
+    for (i, key) in keys.into_iter().enumerate() {
+        first.entry(key).or_insert(i);
+    }
+    Ok(per_item(rhs, |a| {
+        NN::nd(E::INT(*first.get(&key_of(a)).unwrap_or(&missing) as i32))
+    }))
+}
+
+/// Dyadic ∊: whether each item of lhs occurs in rhs.
+pub fn do_member(lhs: &NN, rhs: &NN) -> VmRes {
+    let set: HashSet<Key> = keys_of(rhs).into_iter().collect();
+    Ok(per_item(lhs, |w| NN::nd(E::BOOL(set.contains(&key_of(w))))))
+}
+
+/// The items of a list, or an atom, left after dropping those that are
+/// (or are not) in `other`.
+fn keep_items(n: &NN, other: &NN, keep_found: bool) -> NN {
+    let set: HashSet<Key> = keys_of(other).into_iter().collect();
+    let kept = items_of(n)
+        .into_iter()
+        .filter(|item| set.contains(&key_of(item)) == keep_found)
+        .collect();
+    NN::nd(E::LIST(kept))
+}
+
+/// Monadic ∪: unique. The first occurrence of each item, in order.
+pub fn mo_unique(rhs: &NN) -> VmRes {
+    let mut seen = HashSet::new();
+    let unique = items_of(rhs)
+        .into_iter()
+        .filter(|item| seen.insert(key_of(item)))
+        .collect();
+    Ok(NN::nd(E::LIST(unique)))
+}
+
+/// Dyadic ∪: union. All of lhs, then the unique items of rhs it lacks.
+pub fn do_union(lhs: &NN, rhs: &NN) -> VmRes {
+    let extra = mo_unique(&keep_items(rhs, lhs, false))?;
+    do_cat(lhs, &extra)
+}
+
+/// Dyadic ∩: intersection. The items of lhs that are also in rhs.
+pub fn do_intersect(lhs: &NN, rhs: &NN) -> VmRes {
+    Ok(keep_items(lhs, rhs, true))
+}
+
+/// Dyadic ~: without. The items of lhs that are not in rhs.
+pub fn do_without(lhs: &NN, rhs: &NN) -> VmRes {
+    Ok(keep_items(lhs, rhs, false))
+}
+
+/// Split values into one list per distinct key, in the order each key first
+/// occurs. Without values, each group holds the positions of its key.
+fn groups(sym: &str, keys: &NN, values: Option<&NN>) -> Result<Vec<NN>, VMError> {
+    let keys = keys_of(keys);
+    let values = match values {
+        Some(v) => items_of(v),
+        None => (0..keys.len()).map(|i| NN::nd(E::INT(i as i32))).collect(),
+    };
+    if keys.len() != values.len() {
+        return Err(VMError::new(format!(
+            "{}: length mismatch: {} keys for {} values",
+            sym,
+            keys.len(),
+            values.len()
+        )));
+    }
+    let mut slot: HashMap<Key, usize> = HashMap::new();
+    let mut groups: Vec<Vec<NN>> = vec![];
+    for (k, v) in keys.into_iter().zip(values) {
+        let i = *slot.entry(k).or_insert_with(|| {
+            groups.push(vec![]);
+            groups.len() - 1
+        });
+        groups[i].push(v);
+    }
+    Ok(groups.into_iter().map(|g| NN::nd(E::LIST(g))).collect())
+}
+
+/// Monadic ⊔: group. The positions of each distinct key, in the order the
+/// keys first occur, so that the groups line up with ∪ of the keys.
+pub fn mo_group(rhs: &NN) -> VmRes {
+    Ok(NN::nd(E::LIST(groups("⊔", rhs, None)?)))
+}
+
+/// Dyadic ⊔: the items of rhs, grouped by the matching keys in lhs.
+pub fn do_group(lhs: &NN, rhs: &NN) -> VmRes {
+    Ok(NN::nd(E::LIST(groups("⊔", lhs, Some(rhs))?)))
+}
+
 // ---------------------------------------------------------------------------
 // Shape / Reshape (ρ)
 // ---------------------------------------------------------------------------
@@ -935,3 +2283,60 @@ pub fn do_rho(lhs: &NN, rhs: &NN) -> VmRes {
 
     Ok(build_nested(&shape, &data))
 }
+
+// ---------------------------------------------------------------------------
+// Indexing (@)
+// ---------------------------------------------------------------------------
+
+/// Dyadic @: select from lhs by the index rhs.
+pub fn do_at(lhs: &NN, rhs: &NN) -> VmRes {
+    index(lhs, std::slice::from_ref(rhs))
+}
+
+/// Select from x with one index per axis. An int picks a single item; a list
+/// of indices picks each of them, so the result takes the shape of the index.
+fn index(x: &NN, indices: &[NN]) -> VmRes {
+    let (first, rest) = match indices.split_first() {
+        Some(split) => split,
+        None => return Ok(x.clone()),
+    };
+    match &first.n {
+        E::INT(i) => index(&pick(x, *i)?, rest),
+        E::LIST(l) => {
+            let items: Result<Vec<NN>, VMError> = l
+                .iter()
+                .map(|i| {
+                    let mut path = vec![i.clone()];
+                    path.extend_from_slice(rest);
+                    index(x, &path)
+                })
+                .collect();
+            Ok(NN::nd(E::LIST(items?)))
+        }
+        _ => Err(VMError::new(format!(
+            "@: index must be an int or a list of ints, got {}",
+            type_name(first)
+        ))),
+    }
+}
+
+/// Pick the item at position i of a list.
+fn pick(x: &NN, i: i32) -> VmRes {
+    match &x.n {
+        E::LIST(l) => usize::try_from(i)
+            .ok()
+            .and_then(|u| l.get(u))
+            .cloned()
+            .ok_or_else(|| {
+                VMError::new(format!(
+                    "@: index {} out of range for length {}",
+                    i,
+                    l.len()
+                ))
+            }),
+        _ => Err(VMError::new(format!(
+            "@: cannot index into {}",
+            type_name(x)
+        ))),
+    }
+}
@@ -0,0 +1,202 @@
+// README examples: every line marked `; =>` is run and its output checked,
+// so the documentation cannot drift from the language.
+//
+// The README writes applications the way they are typed at the REPL, without
+// the `|` that ends a train, so each example gets its `|` put back first.
+
+use l::{
+    byte::{Env, I},
+    vm::V,
+};
+
+const SPECIAL: [char; 5] = ['λ', '↻', '⟳', ':', '?'];
+
+/// Cut a line at its comment. A `;` inside brackets separates indices.
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let (mut depth, mut quoted) = (0, false);
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' if !quoted => depth += 1,
+            ']' if !quoted => depth -= 1,
+            ';' if !quoted && depth == 0 => return (&line[..i], Some(&line[i + 1..])),
+            _ => {}
+        }
+    }
+    (line, None)
+}
+
+/// Insert the `|` after the train at the head of each application.
+fn pipe_trains(src: &str) -> String {
+    let chars: Vec<char> = src.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        out.push(c);
+        i += 1;
+        match c {
+            '"' => {
+                while i < chars.len() {
+                    out.push(chars[i]);
+                    i += 1;
+                    if chars[i - 1] == '"' {
+                        break;
+                    }
+                }
+            }
+            // λ is followed by its parameter list, which is not an application
+            'λ' => {
+                while i < chars.len() && chars[i] != ')' {
+                    out.push(chars[i]);
+                    i += 1;
+                }
+            }
+            '(' => {
+                let head = chars[i..].iter().take_while(|c| c.is_whitespace()).count();
+                let first = chars.get(i + head).copied().unwrap_or(')');
+                let second = chars.get(i + head + 1).copied().unwrap_or(' ');
+                let literal = first.is_ascii_digit()
+                    || (first == '-' && second.is_ascii_digit())
+                    || matches!(first, '(' | ')' | '"')
+                    || SPECIAL.contains(&first);
+                if !literal {
+                    while i < chars.len() && !chars[i].is_whitespace() && chars[i] != ')' {
+                        out.push(chars[i]);
+                        i += 1;
+                    }
+                    out.push('|');
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn depth(src: &str) -> i32 {
+    let mut quoted = false;
+    src.chars().fold(0, |d, c| match c {
+        '"' => {
+            quoted = !quoted;
+            d
+        }
+        '(' if !quoted => d + 1,
+        ')' if !quoted => d - 1,
+        _ => d,
+    })
+}
+
+/// Run one expression as the REPL would, keeping the environment.
+fn run(src: &str, env: &mut Env) -> String {
+    match I::fstring_with_env(&pipe_trains(src), env.clone()) {
+        Ok(b) => {
+            let mut vm = V::new(b);
+            vm.r();
+            *env = vm.env();
+            match (&vm.error, vm.pop_last()) {
+                (Some(e), _) => e.to_string(),
+                (None, Some(result)) => result.to_string(),
+                (None, None) => "(no result)".to_string(),
+            }
+        }
+        Err(e) => format!("compile error: {}", e),
+    }
+}
+
+/// A result matches if it equals the expected text, or the expected text is
+/// the result followed by a note in parentheses.
+fn matches(actual: &str, expected: &str) -> bool {
+    let actual: Vec<&str> = actual.lines().map(str::trim).collect();
+    let expected: Vec<&str> = expected.lines().map(str::trim).collect();
+    actual.len() == expected.len()
+        && actual.iter().zip(&expected).enumerate().all(|(i, (a, e))| {
+            a == e
+                || (i == actual.len() - 1 && e.strip_prefix(a).is_some_and(|n| n.starts_with(" (")))
+        })
+}
+
+/// Check every `; =>` example in a markdown file. Each code block is one
+/// session, so a definition is visible to the lines after it. A result on a
+/// line of its own belongs to the expression before it.
+fn check_examples(doc: &str) -> Vec<String> {
+    let mut failures = vec![];
+    let (mut in_block, mut in_code) = (false, false);
+    let mut env = Env::default();
+    let mut src = String::new();
+    // The last complete expression and what it printed
+    let mut last = (String::new(), String::new());
+    let mut expected: Option<String> = None;
+    let mut lines = doc.lines().peekable();
+    while let Some(line) = lines.next() {
+        // Examples are in unlabelled blocks; shell commands are not
+        if line.starts_with("```") {
+            in_block = !in_block;
+            in_code = in_block && line == "```";
+            env = Env::default();
+            src.clear();
+            continue;
+        }
+        if !in_code {
+            continue;
+        }
+        let (code, comment) = split_comment(line);
+        src.push_str(code);
+        src.push(' ');
+        if let Some(result) = comment.and_then(|c| c.trim().strip_prefix("=>")) {
+            let mut result = result.trim().to_string();
+            // A result on several lines continues in comment-only lines
+            while let Some(next) = lines.peek() {
+                match next.trim_start().strip_prefix(';') {
+                    Some(more) if !more.trim_start().starts_with("=>") => {
+                        result.push('\n');
+                        result.push_str(more.trim());
+                        lines.next();
+                    }
+                    _ => break,
+                }
+            }
+            expected = Some(result);
+        }
+        if depth(&src) > 0 {
+            continue;
+        }
+        if !src.trim().is_empty() {
+            last = (src.trim().to_string(), run(src.trim(), &mut env));
+            src.clear();
+        }
+        if let Some(expected) = expected.take() {
+            if !matches(&last.1, &expected) {
+                failures.push(format!(
+                    "{}\n  expected: {}\n  actual:   {}",
+                    last.0,
+                    expected.replace('\n', " / "),
+                    last.1.replace('\n', " / ")
+                ));
+            }
+        }
+    }
+    failures
+}
+
+#[test]
+fn readme_examples() {
+    let failures = check_examples(include_str!("../README.md"));
+    assert!(
+        failures.is_empty(),
+        "{} README examples are out of date:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn manual_examples() {
+    let failures = check_examples(include_str!("../manual.md"));
+    assert!(
+        failures.is_empty(),
+        "{} manual examples are out of date:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
@@ -24,6 +24,7 @@ fn parse_negative_int() {
 }
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn parse_float() {
     let ast = parse_ok("3.14");
     assert_eq!(ast.len(), 1);
@@ -189,3 +190,102 @@ fn parse_comment() {
     assert_eq!(ast.len(), 1);
     assert_eq!(ast[0].n, E::INT(42));
 }
+
+#[test]
+fn parse_name_each() {
+    // (fǁ| xs) → train [MCO{VAL(f), ǁ}], not a name "fǁ"
+    let ast = parse_ok("(fǁ| xs)");
+    match &ast[0].n {
+        E::APPLY { train, .. } => match &train[0].n {
+            E::MCO { o, co } => {
+                assert_eq!(o.n, E::VAL("f".to_string()));
+                assert_eq!(co.n, E::CN(CN::Each));
+            }
+            other => panic!("expected MCO, got {:?}", other),
+        },
+        other => panic!("expected APPLY, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_chained_combinators() {
+    // (+/⌸| k v) → train [MCO{MCO{+, /}, ⌸}]: ⌸ takes the derived +/
+    let ast = parse_ok("(+/⌸| k v)");
+    match &ast[0].n {
+        E::APPLY { train, .. } => {
+            assert_eq!(train.len(), 1);
+            match &train[0].n {
+                E::MCO { o, co } => {
+                    assert_eq!(co.n, E::CN(CN::Key));
+                    match &o.n {
+                        E::MCO { o, co } => {
+                            assert_eq!(o.n, E::MFN(FN::Plus));
+                            assert_eq!(co.n, E::CN(CN::Fold));
+                        }
+                        other => panic!("expected MCO, got {:?}", other),
+                    }
+                }
+                other => panic!("expected MCO, got {:?}", other),
+            }
+        }
+        other => panic!("expected APPLY, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_cond() {
+    let ast = parse_ok("(? a 1 b 2 3)");
+    match &ast[0].n {
+        E::COND { arms, otherwise } => {
+            assert_eq!(arms.len(), 2);
+            assert_eq!(arms[0].0.n, E::VAL("a".to_string()));
+            assert_eq!(arms[1].1.n, E::INT(2));
+            assert_eq!(otherwise.n, E::INT(3));
+        }
+        other => panic!("expected COND, got {:?}", other),
+    }
+    // A conditional needs an else branch
+    assert!(parse("(? a 1)").is_err());
+}
+
+#[test]
+fn parse_while() {
+    let ast = parse_ok("(⟳ (<| i 3) (: i (+| i 1)))");
+    match &ast[0].n {
+        E::WHILE { test, body } => {
+            assert!(matches!(test.n, E::APPLY { .. }));
+            assert_eq!(body.len(), 1);
+        }
+        other => panic!("expected WHILE, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_inner_product() {
+    // (+.×| a b) → train [INNER(Plus, Mult)]
+    let ast = parse_ok("(+.×| a b)");
+    match &ast[0].n {
+        E::APPLY { train, args } => {
+            assert_eq!(train.len(), 1);
+            assert_eq!(train[0].n, E::INNER(FN::Plus, FN::Mult));
+            assert_eq!(args.len(), 2);
+        }
+        other => panic!("expected APPLY, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_bracket_index() {
+    // m[i;j] → (@| m i j)
+    let ast = parse_ok("m[i;j]");
+    match &ast[0].n {
+        E::APPLY { train, args } => {
+            assert_eq!(train[0].n, E::MFN(FN::At));
+            assert_eq!(args.len(), 3);
+            assert_eq!(args[0].n, E::VAL("m".to_string()));
+        }
+        other => panic!("expected APPLY, got {:?}", other),
+    }
+    // A space before [ is not indexing
+    assert!(parse("(f| m [0])").is_err());
+}
@@ -12,6 +12,24 @@ fn assert_pop_last(source: &str, node: NN) {
     assert_eq!(&node, vm.pop_last().unwrap());
 }
 
+fn assert_err(source: &str, msg: &str) {
+    let mut vm = V::new(I::fstring(source).unwrap());
+    vm.r();
+    assert_eq!(msg, vm.error.expect("expected a VM error").msg);
+}
+
+fn ints(v: &[i32]) -> NN {
+    NN::nd(E::LIST(v.iter().map(|i| NN::nd(E::INT(*i))).collect()))
+}
+
+fn rows(v: &[&[i32]]) -> NN {
+    NN::nd(E::LIST(v.iter().map(|r| ints(r)).collect()))
+}
+
+fn bools(v: &[bool]) -> NN {
+    NN::nd(E::LIST(v.iter().map(|b| NN::nd(E::BOOL(*b))).collect()))
+}
+
 #[test]
 fn unary() {
     // (-| 2) → -2
@@ -174,3 +192,817 @@ fn lambda_in_dyadic_train() {
     );
     assert_eq!(&expected, vm2.pop_last().unwrap());
 }
+
+#[test]
+fn mult() {
+    // (×| 3 4) → 12
+    assert_pop_last("(×| 3 4)", NN::nd(E::INT(12)));
+    // (×| 2 1.5) → 3.0 (int promotes to float)
+    assert_pop_last("(×| 2 1.5)", NN::nd(E::FT(3.0)));
+    // (×| (1 2 3) (4 5 6)) → 4 10 18
+    assert_pop_last(
+        "(×| (1 2 3) (4 5 6))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::INT(4)),
+            NN::nd(E::INT(10)),
+            NN::nd(E::INT(18)),
+        ])),
+    );
+    // An int product that overflows promotes to float
+    assert_pop_last("(×| 100000 100000)", NN::nd(E::FT(1e10)));
+    assert_pop_last("(×| -100000 100000)", NN::nd(E::FT(-1e10)));
+}
+
+#[test]
+fn sign() {
+    // (×| (-3 0 2.5)) → -1 0 1
+    assert_pop_last(
+        "(×| (-3 0 2.5))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::INT(-1)),
+            NN::nd(E::INT(0)),
+            NN::nd(E::INT(1)),
+        ])),
+    );
+}
+
+#[test]
+fn dot_product_train() {
+    // (+/×| (1 2 3) (4 5 6)) → 1×4 + 2×5 + 3×6 = 32
+    assert_pop_last("(+/×| (1 2 3) (4 5 6))", NN::nd(E::INT(32)));
+}
+
+#[test]
+fn div() {
+    // (÷| 7 2) → 3.5 (int/int gives a float)
+    assert_pop_last("(÷| 7 2)", NN::nd(E::FT(3.5)));
+    // (÷| 1.5 0.5) → 3.0
+    assert_pop_last("(÷| 1.5 0.5)", NN::nd(E::FT(3.0)));
+    // (÷| (2 4) (4 8)) → 0.5 0.5
+    assert_pop_last(
+        "(÷| (2 4) (4 8))",
+        NN::nd(E::LIST(vec![NN::nd(E::FT(0.5)), NN::nd(E::FT(0.5))])),
+    );
+}
+
+#[test]
+fn reciprocal() {
+    // (÷| 4) → 0.25
+    assert_pop_last("(÷| 4)", NN::nd(E::FT(0.25)));
+}
+
+#[test]
+fn div_by_zero() {
+    assert_err("(÷| 1 0)", "division by zero");
+    assert_err("(÷| 0)", "division by zero");
+}
+
+#[test]
+fn each_monadic() {
+    // (-ǁ| (1 2 3)) → -1 -2 -3
+    assert_pop_last(
+        "(-ǁ| (1 2 3))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::INT(-1)),
+            NN::nd(E::INT(-2)),
+            NN::nd(E::INT(-3)),
+        ])),
+    );
+    // (!ǁ| (1 2)) → (0) (0 1)
+    assert_pop_last(
+        "(!ǁ| (1 2))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::LIST(vec![NN::nd(E::INT(0))])),
+            NN::nd(E::LIST(vec![NN::nd(E::INT(0)), NN::nd(E::INT(1))])),
+        ])),
+    );
+}
+
+#[test]
+fn each_dyadic() {
+    // (ρǁ| (1 2) (7 8)) → (7) (8 8)
+    assert_pop_last(
+        "(ρǁ| (1 2) (7 8))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::LIST(vec![NN::nd(E::INT(7))])),
+            NN::nd(E::LIST(vec![NN::nd(E::INT(8)), NN::nd(E::INT(8))])),
+        ])),
+    );
+    // An atom extends to match the list: (ρǁ| 2 (7 8)) → (7 7) (8 8)
+    assert_pop_last(
+        "(ρǁ| 2 (7 8))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::LIST(vec![NN::nd(E::INT(7)), NN::nd(E::INT(7))])),
+            NN::nd(E::LIST(vec![NN::nd(E::INT(8)), NN::nd(E::INT(8))])),
+        ])),
+    );
+}
+
+#[test]
+fn each_length_mismatch() {
+    assert_err(
+        "(ρǁ| (1 2) (7 8 9))",
+        "each (ǁ) list length mismatch: 2 vs 3",
+    );
+}
+
+#[test]
+fn each_lambda() {
+    // Map a user function over a list: (sqǁ| (1 2 3)) → 2 5 10
+    assert_pop_last(
+        "(↻ (: sq (λ (x) (+| (×| x x) 1))) (sqǁ| (1 2 3)))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::INT(2)),
+            NN::nd(E::INT(5)),
+            NN::nd(E::INT(10)),
+        ])),
+    );
+    // Zip two lists through a dyadic user function
+    assert_pop_last(
+        "(↻ (: f (λ (a b) (-| (×| a 10) b))) (fǁ| (1 2) (3 4)))",
+        NN::nd(E::LIST(vec![NN::nd(E::INT(7)), NN::nd(E::INT(16))])),
+    );
+}
+
+#[test]
+fn nested_dyadic() {
+    // A dyadic apply as the left arg must not leak its operands
+    assert_pop_last("(+| (×| 2 3) 1)", NN::nd(E::INT(7)));
+    assert_pop_last("(-| (-| 5 1) (-| 10 2))", NN::nd(E::INT(-4)));
+}
+
+#[test]
+fn scalar_extension() {
+    // (+| 1 (!| 3)) → 1 2 3
+    assert_pop_last(
+        "(+| 1 (!| 3))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::INT(1)),
+            NN::nd(E::INT(2)),
+            NN::nd(E::INT(3)),
+        ])),
+    );
+    // Atom against a nested list: (-| ((1 2) (3 4)) 1) → (0 1) (2 3)
+    assert_pop_last(
+        "(-| ((1 2) (3 4)) 1)",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::LIST(vec![NN::nd(E::INT(0)), NN::nd(E::INT(1))])),
+            NN::nd(E::LIST(vec![NN::nd(E::INT(2)), NN::nd(E::INT(3))])),
+        ])),
+    );
+    // Leading axes agree: each row pairs with one item
+    assert_pop_last(
+        "(¯| (10 0) ((1 20) (3 4)))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::LIST(vec![NN::nd(E::INT(10)), NN::nd(E::INT(20))])),
+            NN::nd(E::LIST(vec![NN::nd(E::INT(3)), NN::nd(E::INT(4))])),
+        ])),
+    );
+}
+
+#[test]
+fn shape_mismatch() {
+    assert_err(
+        "(+| (ρ!:| (3 2) 6) (ρ!:| (2 3) 6))",
+        "shape mismatch: 2×3 vs 3×2",
+    );
+}
+
+#[test]
+fn numeric_tower() {
+    // int + float → float
+    assert_pop_last("(+| 1 2.5)", NN::nd(E::FT(3.5)));
+    // fold promotes as it goes
+    assert_pop_last("(+/| (1 2.5 3))", NN::nd(E::FT(6.5)));
+    // bool + float → float: (=| 0) is true
+    assert_pop_last("(+| (=| 0) 2.5)", NN::nd(E::FT(3.5)));
+    // min and mod across the tower
+    assert_pop_last("(_| 2 3.5)", NN::nd(E::FT(2.0)));
+    assert_pop_last("(!| 2 7.5)", NN::nd(E::FT(1.5)));
+    // monadic negate pervades lists of mixed numbers
+    assert_pop_last(
+        "(-| (1 2.5))",
+        NN::nd(E::LIST(vec![NN::nd(E::INT(-1)), NN::nd(E::FT(-2.5))])),
+    );
+}
+
+#[test]
+fn int_overflow() {
+    // sums and differences that leave the int range promote to float
+    assert_pop_last("(+| 2147483647 1)", NN::nd(E::FT(2147483648.0)));
+    assert_pop_last("(-| -2147483648 1)", NN::nd(E::FT(-2147483649.0)));
+    assert_pop_last("(+/| (2147483647 1))", NN::nd(E::FT(2147483648.0)));
+    // so does negating the most negative int
+    assert_pop_last("(-| -2147483648)", NN::nd(E::FT(2147483648.0)));
+    // and its remainder by -1 is 0 rather than a panic
+    assert_pop_last("(!| -1 -2147483648)", NN::nd(E::INT(0)));
+}
+
+#[test]
+fn comparisons() {
+    assert_pop_last("(<| (1 2 3) 2)", bools(&[true, false, false]));
+    assert_pop_last("(>| (1 2 3) 2)", bools(&[false, false, true]));
+    assert_pop_last("(≤| (1 2 3) 2)", bools(&[true, true, false]));
+    assert_pop_last("(≥| 2.5 (1 3))", bools(&[true, false]));
+    assert_pop_last("(=| (1 2 3) (1 0 3))", bools(&[true, false, true]));
+    assert_pop_last("(≠| (1 2 3) (1 0 3))", bools(&[false, true, false]));
+    assert_pop_last("(<| \"abc\" \"abd\")", NN::nd(E::BOOL(true)));
+    assert_pop_last("(=| 1 \"a\")", NN::nd(E::BOOL(false)));
+    // comparisons build masks for &
+    assert_pop_last(
+        "(&| (>| (!| 5) 2) (!| 5))",
+        NN::nd(E::LIST(vec![NN::nd(E::INT(3)), NN::nd(E::INT(4))])),
+    );
+}
+
+#[test]
+fn compare_mismatch() {
+    assert_err("(<| 1 \"a\")", "< cannot compare int and string");
+}
+
+#[test]
+fn lambda_combinators() {
+    // Custom reduction: fold a user function over a list
+    assert_pop_last(
+        "(↻ (: f (λ (a b) (+| (×| 2 a) b))) (f/| (1 2 3)))",
+        NN::nd(E::INT(11)),
+    );
+    // Each-left with a user function
+    assert_pop_last(
+        "(↻ (: f (λ (a b) (-| a b))) (f⊣| (1 2) (10 20)))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::LIST(vec![NN::nd(E::INT(-9)), NN::nd(E::INT(-19))])),
+            NN::nd(E::LIST(vec![NN::nd(E::INT(-8)), NN::nd(E::INT(-18))])),
+        ])),
+    );
+}
+
+#[test]
+fn call_many_args() {
+    assert_pop_last(
+        "(↻ (: clamp (λ (lo hi x) (_| hi (¯| lo x)))) (clamp| 0 10 (-5 3 12)))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::INT(0)),
+            NN::nd(E::INT(3)),
+            NN::nd(E::INT(10)),
+        ])),
+    );
+    // A call nested inside a dyadic apply
+    assert_pop_last(
+        "(↻ (: f (λ (a b c d) (+| a (+| b (+| c d))))) (+| 1 (f| 1 2 3 4)))",
+        NN::nd(E::INT(11)),
+    );
+}
+
+#[test]
+fn call_arity_error() {
+    assert_err(
+        "(↻ (: f (λ (a b) (+| a b))) (f| 1 2 3))",
+        "function f expects 2 args, got 3",
+    );
+    assert_err(
+        "(↻ (: f (λ (a b c) a)) (f| 1 2))",
+        "function f expects 3 args, got 2",
+    );
+}
+
+#[test]
+fn closures() {
+    assert_pop_last(
+        "(↻ (: make_adder (λ (n) (λ (x) (+| n x)))) (: add5 (make_adder| 5)) (add5| 10))",
+        NN::nd(E::INT(15)),
+    );
+    // Captured bindings survive through several levels of nesting
+    assert_pop_last(
+        "(↻ (: mk (λ (n) (λ (m) (λ (x) (+| n (+| m x)))))) (: a (mk| 1)) (: b (a| 10)) (b| 100))",
+        NN::nd(E::INT(111)),
+    );
+    // Globals resolve at call time, so g can be defined after f
+    assert_pop_last(
+        "(↻ (: f (λ (x) (g| x))) (: g (λ (x) (×| x 2))) (f| 21))",
+        NN::nd(E::INT(42)),
+    );
+}
+
+#[test]
+fn closures_across_lines() {
+    // A global x must not shadow a parameter called x
+    let b0 = I::fstring("(: x 1)").unwrap();
+    let mut vm0 = V::new(b0);
+    vm0.r();
+    assert!(vm0.error.is_none(), "VM error: {}", vm0.error.unwrap());
+
+    let b1 = I::fstring_with_env("(: make_adder (λ (n) (λ (x) (+| n x))))", vm0.env()).unwrap();
+    let mut vm1 = V::new(b1);
+    vm1.r();
+    assert!(vm1.error.is_none(), "VM error: {}", vm1.error.unwrap());
+
+    let b2 = I::fstring_with_env("(: add5 (make_adder| 5))", vm1.env()).unwrap();
+    let mut vm2 = V::new(b2);
+    vm2.r();
+    assert!(vm2.error.is_none(), "VM error: {}", vm2.error.unwrap());
+
+    let b3 = I::fstring_with_env("(add5| 10)", vm2.env()).unwrap();
+    let mut vm3 = V::new(b3);
+    vm3.r();
+    assert!(vm3.error.is_none(), "VM error: {}", vm3.error.unwrap());
+    assert_eq!(&NN::nd(E::INT(15)), vm3.pop_last().unwrap());
+}
+
+#[test]
+fn recursion_depth_limit() {
+    // Runs on a 2MB test thread, so the default limit must not overflow it
+    assert_err(
+        "(↻ (: f (λ (x) (+| 1 (f| x)))) (f| 1))",
+        "in user function: recursion depth limit (500) exceeded",
+    );
+}
+
+#[test]
+fn deep_recursion() {
+    // Deep non-tail recursion grows the stack rather than overflowing it
+    let mut vm = V::new(
+        I::fstring("(↻ (: f (λ (x) (? (=| x 0) 0 (+| 1 (f| (-| x 1)))))) (f| 5000))").unwrap(),
+    );
+    vm.max_depth = 10000;
+    vm.r();
+    assert!(vm.error.is_none());
+    assert_eq!(&NN::nd(E::INT(5000)), vm.pop_last().unwrap());
+}
+
+#[test]
+fn local_recursion() {
+    // A function defined inside another one can call itself by name.
+    // f k builds the list of f 0 .. f k-1, so f 0 is () and f 2 is (() (()))
+    let empty = || NN::nd(E::LIST(vec![]));
+    assert_pop_last(
+        "(↻ (: outer (λ (n) (↻ (: f (λ (k) (fǁ| (!| k)))) (f| n)))) (outer| 2))",
+        NN::nd(E::LIST(vec![empty(), NN::nd(E::LIST(vec![empty()]))])),
+    );
+}
+
+#[test]
+fn tail_calls() {
+    // f and g only tail-call, so the chain runs at the depth of the first call
+    let src =
+        "(↻ (: h (λ (x) (+| x 1))) (: g (λ (x) (h| x))) (: f (λ (x) (↻ (: y 2) (g| y)))) (f| 1))";
+    let mut vm = V::new(I::fstring(src).unwrap());
+    vm.max_depth = 1;
+    vm.r();
+    assert!(vm.error.is_none(), "VM error: {}", vm.error.unwrap());
+    assert_eq!(&NN::nd(E::INT(3)), vm.pop_last().unwrap());
+
+    // A call that is not in tail position still nests
+    let src = "(↻ (: g (λ (x) (+| x 1))) (: f (λ (x) (+| 1 (g| x)))) (f| 1))";
+    let mut vm = V::new(I::fstring(src).unwrap());
+    vm.max_depth = 1;
+    vm.r();
+    assert_eq!(
+        "in user function: recursion depth limit (1) exceeded",
+        vm.error.unwrap().msg
+    );
+}
+
+#[test]
+fn cond() {
+    assert_pop_last("(+| 1 (? 0 10 20))", NN::nd(E::INT(21)));
+    // Only the chosen branch runs
+    assert_pop_last("(? (=| 0 0) 1 (÷| 1 0))", NN::nd(E::INT(1)));
+    assert_pop_last(
+        "(↻ (: s (λ (x) (? (<| x 0) \"neg\" (=| x 0) \"zero\" \"pos\"))) (sǁ| (-3 0 5)))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::ST("neg".to_string())),
+            NN::nd(E::ST("zero".to_string())),
+            NN::nd(E::ST("pos".to_string())),
+        ])),
+    );
+    assert_err("(? (1 2) 1 2)", "? expects a boolean test, got list");
+}
+
+#[test]
+fn recursive_functions() {
+    assert_pop_last(
+        "(↻ (: fact (λ (n) (? (=| n 0) 1 (×| n (fact| (-| n 1)))))) (fact| 5))",
+        NN::nd(E::INT(120)),
+    );
+    assert_pop_last(
+        "(↻ (: fib (λ (n) (? (<| n 2) n (+| (fib| (-| n 1)) (fib| (-| n 2)))))) (fib| 10))",
+        NN::nd(E::INT(55)),
+    );
+    // A tail-recursive loop far deeper than the recursion limit
+    assert_pop_last(
+        "(↻ (: sum (λ (n acc) (? (=| n 0) acc (sum| (-| n 1) (+| acc n))))) (sum| 10000 0))",
+        NN::nd(E::INT(50005000)),
+    );
+}
+
+#[test]
+fn while_loop() {
+    assert_pop_last(
+        "(↻ (: i 0) (: s 0) (⟳ (<| i 5) (: s (+| s i)) (: i (+| i 1))) s)",
+        NN::nd(E::INT(10)),
+    );
+    // The loop's value is the body's last value, or () if it never ran
+    assert_pop_last("(↻ (: i 0) (⟳ (<| i 3) (: i (+| i 1))))", NN::nd(E::INT(3)));
+    assert_pop_last(
+        "(↻ (: i 0) (⟳ (<| i 0) (: i (+| i 1))))",
+        NN::nd(E::LIST(vec![])),
+    );
+    // Parameters are mutable bindings too
+    assert_pop_last(
+        "(↻ (: f (λ (n) (↻ (: acc 1) (⟳ (>| n 0) (: acc (×| acc n)) (: n (-| n 1))) acc))) (f| 5))",
+        NN::nd(E::INT(120)),
+    );
+}
+
+#[test]
+fn while_loop_errors() {
+    assert_err("(⟳ (1 2) 1)", "⟳ expects a boolean test, got list");
+}
+
+#[test]
+fn while_loop_across_lines() {
+    // i is compiled as a constant slot on the second line; rebinding it must
+    // update that slot or the loop would never end
+    let b1 = I::fstring("(: i 0)").unwrap();
+    let mut vm1 = V::new(b1);
+    vm1.r();
+    let b2 = I::fstring_with_env("(⟳ (<| i 3) (: i (+| i 1)))", vm1.env()).unwrap();
+    let mut vm2 = V::new(b2);
+    vm2.r();
+    assert!(vm2.error.is_none(), "VM error: {}", vm2.error.unwrap());
+    assert_eq!(&NN::nd(E::INT(3)), vm2.pop_last().unwrap());
+}
+
+#[test]
+fn converge() {
+    assert_pop_last("(_⍟| 3.7)", NN::nd(E::INT(3)));
+    assert_pop_last(
+        "(↻ (: h (λ (x) (_| (÷| x 2)))) (h⍟| 100))",
+        NN::nd(E::INT(0)),
+    );
+    // Monadic-only in a dyadic train: converge the sum
+    assert_pop_last(
+        "(↻ (: h (λ (x) (_| (÷| x 2)))) (h⍟+| 50 50))",
+        NN::nd(E::INT(0)),
+    );
+}
+
+#[test]
+fn power() {
+    assert_pop_last("(-⍣| 3 5)", NN::nd(E::INT(-5)));
+    assert_pop_last("(-⍣| 0 5)", NN::nd(E::INT(5)));
+    assert_pop_last(
+        "(↻ (: double (λ (x) (×| x 2))) (double⍣| 10 1))",
+        NN::nd(E::INT(1024)),
+    );
+    // Predicate form: apply until the predicate holds
+    assert_pop_last(
+        "(↻ (: double (λ (x) (×| x 2))) (: big (λ (x) (>| x 100))) (double⍣| big 1))",
+        NN::nd(E::INT(128)),
+    );
+    assert_pop_last(
+        "(↻ (: double (λ (x) (×| x 2))) (double⍣| (λ (x) (>| x 100)) 200))",
+        NN::nd(E::INT(200)),
+    );
+}
+
+#[test]
+fn power_errors() {
+    assert_err("(-⍣| -1 5)", "⍣ count must not be negative, got -1");
+    assert_err(
+        "(-⍣| (1 2) 5)",
+        "⍣ expects a count or a predicate function, got list",
+    );
+    assert_err(
+        "(-⍣| (λ (x) (1 2)) 5)",
+        "⍣ predicate must return a boolean, got list",
+    );
+}
+
+#[test]
+fn scan() {
+    assert_pop_last(
+        "(+\\| (1 2 3))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::INT(1)),
+            NN::nd(E::INT(3)),
+            NN::nd(E::INT(6)),
+        ])),
+    );
+    assert_pop_last(
+        "(↻ (: f (λ (a b) (×| a b))) (f\\| (1 2 3 4)))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::INT(1)),
+            NN::nd(E::INT(2)),
+            NN::nd(E::INT(6)),
+            NN::nd(E::INT(24)),
+        ])),
+    );
+    // Monadic-only in a dyadic train: scan the sum
+    assert_pop_last(
+        "(+\\+| (1 2) (3 4))",
+        NN::nd(E::LIST(vec![NN::nd(E::INT(4)), NN::nd(E::INT(10))])),
+    );
+}
+
+#[test]
+fn each_left() {
+    assert_pop_last(
+        "(+⊣| (1 2) 10)",
+        NN::nd(E::LIST(vec![NN::nd(E::INT(11)), NN::nd(E::INT(12))])),
+    );
+}
+
+#[test]
+fn outer_product() {
+    assert_pop_last(
+        "(×∘| (1 2 3) (1 2 3 4))",
+        NN::nd(E::LIST(vec![
+            ints(&[1, 2, 3, 4]),
+            ints(&[2, 4, 6, 8]),
+            ints(&[3, 6, 9, 12]),
+        ])),
+    );
+    assert_pop_last(
+        "(↻ (: f (λ (a b) (-| a b))) (f∘| (1 2) (10 20)))",
+        NN::nd(E::LIST(vec![ints(&[-9, -19]), ints(&[-8, -18])])),
+    );
+    assert_pop_last("(+∘| 1 2)", NN::nd(E::INT(3)));
+}
+
+#[test]
+fn inner_product() {
+    assert_pop_last(
+        "(+.×| ((1 2) (3 4)) ((5 6) (7 8)))",
+        NN::nd(E::LIST(vec![ints(&[19, 22]), ints(&[43, 50])])),
+    );
+    assert_pop_last(
+        "(+.×| ((1 2 3) (4 5 6)) ((1 2) (3 4) (5 6)))",
+        NN::nd(E::LIST(vec![ints(&[22, 28]), ints(&[49, 64])])),
+    );
+    assert_pop_last("(+.×| (1 2 3) (4 5 6))", NN::nd(E::INT(32)));
+    assert_pop_last("(+.×| ((1 2) (3 4)) (1 1))", ints(&[3, 7]));
+    assert_pop_last("(+.×| (1 1) ((1 2) (3 4)))", ints(&[4, 6]));
+    assert_pop_last("(¯.+| (1 2) (3 4))", NN::nd(E::INT(6)));
+}
+
+#[test]
+fn inner_product_errors() {
+    assert_err(
+        "(+.×| (1 2) (1 2 3))",
+        "inner product length mismatch: 2 and 3",
+    );
+    assert_err(
+        "(+.×| ((1 2) (3)) (1 1))",
+        "inner product expects a rectangular matrix",
+    );
+}
+
+#[test]
+fn index() {
+    assert_pop_last("(@| (10 20 30) 1)", NN::nd(E::INT(20)));
+    assert_pop_last("(@| (10 20 30) (2 0))", ints(&[30, 10]));
+    assert_pop_last("(@| ((1 2) (3 4)) 1 0)", NN::nd(E::INT(3)));
+    // Bracket sugar
+    assert_pop_last("(↻ (: x (10 20 30)) x[1])", NN::nd(E::INT(20)));
+    assert_pop_last("(↻ (: m ((1 2 3) (4 5 6))) m[1;2])", NN::nd(E::INT(6)));
+    assert_pop_last("(↻ (: m ((1 2 3) (4 5 6))) m[(0 1);2])", ints(&[3, 6]));
+    assert_pop_last("(↻ (: m ((1 2 3) (4 5 6))) m[1][0])", NN::nd(E::INT(4)));
+    assert_pop_last(
+        "(10 20 30)[((0 1) (2 2))]",
+        NN::nd(E::LIST(vec![ints(&[10, 20]), ints(&[30, 30])])),
+    );
+}
+
+#[test]
+fn index_out_of_range() {
+    assert_err("(@| (10 20 30) 3)", "@: index 3 out of range for length 3");
+    assert_err(
+        "(↻ (: m ((1 2) (3 4))) m[0;-1])",
+        "@: index -1 out of range for length 2",
+    );
+}
+
+#[test]
+fn grade_and_sort() {
+    assert_pop_last("(⍋| (30 10 20))", ints(&[1, 2, 0]));
+    assert_pop_last("(⍒| (30 10 20))", ints(&[0, 2, 1]));
+    // Stable in both directions
+    assert_pop_last("(⍋| (1 2 1 2))", ints(&[0, 2, 1, 3]));
+    assert_pop_last("(⍒| (1 2 1 2))", ints(&[1, 3, 0, 2]));
+    assert_pop_last(
+        "(∧| (3 1.5 2))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::FT(1.5)),
+            NN::nd(E::INT(2)),
+            NN::nd(E::INT(3)),
+        ])),
+    );
+    assert_pop_last(
+        "(∨| (\"b\" \"a\" \"c\"))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::ST("c".to_string())),
+            NN::nd(E::ST("b".to_string())),
+            NN::nd(E::ST("a".to_string())),
+        ])),
+    );
+    // Nested lists compare lexicographically
+    assert_pop_last(
+        "(∧| ((1 2) (1) (0 5)))",
+        NN::nd(E::LIST(vec![ints(&[0, 5]), ints(&[1]), ints(&[1, 2])])),
+    );
+    assert_pop_last("(↻ (: x (5 3 9)) x[(⍋| x)])", ints(&[3, 5, 9]));
+}
+
+#[test]
+fn reverse_and_rotate() {
+    assert_pop_last("(⌽| (1 2 3))", ints(&[3, 2, 1]));
+    assert_pop_last(
+        "(⌽| ((1 2) (3 4)))",
+        NN::nd(E::LIST(vec![ints(&[3, 4]), ints(&[1, 2])])),
+    );
+    assert_pop_last("(⌽| 1 (1 2 3))", ints(&[2, 3, 1]));
+    assert_pop_last("(⌽| -1 (1 2 3))", ints(&[3, 1, 2]));
+    assert_pop_last("(⌽| 4 (1 2 3))", ints(&[2, 3, 1]));
+}
+
+#[test]
+fn transpose() {
+    let t = NN::nd(E::LIST(vec![ints(&[1, 4]), ints(&[2, 5]), ints(&[3, 6])]));
+    assert_pop_last("(⍉| ((1 2 3) (4 5 6)))", t.clone());
+    assert_pop_last("(⍉| (1 0) ((1 2 3) (4 5 6)))", t);
+    // Rank 3: ρ (4 3 2) has shape 2×3×4; ⍉ gives 4×3×2 with t[k][j][i] = x[i][j][k]
+    assert_pop_last("(@| (⍉| (ρ!:| (4 3 2) 24)) 0 1 1)", NN::nd(E::INT(16)));
+    assert_pop_last(
+        "(@| (⍉| (2 0 1) (ρ!:| (4 3 2) 24)) 1 2 0)",
+        NN::nd(E::INT(6)),
+    );
+
+    assert_err(
+        "(⍉| (1 1) ((1 2) (3 4)))",
+        "⍉: axes must be a permutation of 0 to 1 for rank 2",
+    );
+}
+
+#[test]
+fn take_and_drop() {
+    assert_pop_last("(↑| 2 (1 2 3))", ints(&[1, 2]));
+    assert_pop_last("(↑| -2 (1 2 3))", ints(&[2, 3]));
+    assert_pop_last("(↑| 5 (1 2 3))", ints(&[1, 2, 3, 0, 0]));
+    assert_pop_last("(↑| -4 (1 2 3))", ints(&[0, 1, 2, 3]));
+    assert_pop_last(
+        "(↑| 2 (\"a\"))",
+        NN::nd(E::LIST(vec![
+            NN::nd(E::ST("a".to_string())),
+            NN::nd(E::ST(String::new())),
+        ])),
+    );
+    assert_pop_last("(↓| 1 (1 2 3))", ints(&[2, 3]));
+    assert_pop_last("(↓| -1 (1 2 3))", ints(&[1, 2]));
+    assert_pop_last("(↓| 5 (1 2 3))", ints(&[]));
+}
+
+#[test]
+fn take_and_drop_matrix() {
+    assert_pop_last(
+        "(↑| (3 4) ((1 2 3) (4 5 6)))",
+        NN::nd(E::LIST(vec![
+            ints(&[1, 2, 3, 0]),
+            ints(&[4, 5, 6, 0]),
+            ints(&[0, 0, 0, 0]),
+        ])),
+    );
+    assert_pop_last(
+        "(↑| (-1 -2) ((1 2 3) (4 5 6)))",
+        NN::nd(E::LIST(vec![ints(&[5, 6])])),
+    );
+    assert_pop_last(
+        "(↓| (0 -2) ((1 2 3) (4 5 6)))",
+        NN::nd(E::LIST(vec![ints(&[1]), ints(&[4])])),
+    );
+
+    assert_err("(↑| (1 2 3) (1 2))", "↑: 3 counts for rank 1");
+}
+
+#[test]
+fn ravel_and_catenate() {
+    assert_pop_last("(,| ((1 2) (3 4)))", ints(&[1, 2, 3, 4]));
+    assert_pop_last("(,| ((1 (2 3)) 4))", ints(&[1, 2, 3, 4]));
+    assert_pop_last("(,| (1 2) (3 4))", ints(&[1, 2, 3, 4]));
+    assert_pop_last("(,| (1 2) 3)", ints(&[1, 2, 3]));
+    assert_pop_last("(,| 0 (1 2))", ints(&[0, 1, 2]));
+    assert_pop_last(
+        "(,| ((1 2) (3 4)) (9 8))",
+        NN::nd(E::LIST(vec![ints(&[1, 2, 9]), ints(&[3, 4, 8])])),
+    );
+}
+
+#[test]
+fn catenate_first() {
+    let stacked = NN::nd(E::LIST(vec![ints(&[1, 2]), ints(&[3, 4]), ints(&[5, 6])]));
+    assert_pop_last("(⍪| ((1 2) (3 4)) ((5 6)))", stacked.clone());
+    assert_pop_last("(⍪| ((1 2) (3 4)) (5 6))", stacked);
+    assert_pop_last(
+        "(⍪| ((1 2) (3 4)) 0)",
+        NN::nd(E::LIST(vec![ints(&[1, 2]), ints(&[3, 4]), ints(&[0, 0])])),
+    );
+
+    assert_err("(⍪| ((1 2) (3 4)) (5 6 7))", "⍪: shape mismatch: 2×2 and 3");
+}
+
+#[test]
+fn where_and_replicate() {
+    assert_pop_last("(&| (1 0 1 0) (10 20 30 40))", ints(&[10, 30]));
+    assert_pop_last("(&| (2 0 1) (10 20 30))", ints(&[10, 10, 30]));
+    assert_pop_last("(&| (0 1 0 1))", ints(&[1, 3]));
+    assert_pop_last("(&| (1 0 2))", ints(&[0, 2, 2]));
+    assert_pop_last("(&| (>| (3 1 4 1 5) 2))", ints(&[0, 2, 4]));
+}
+
+#[test]
+fn replicate_errors() {
+    assert_err(
+        "(&| (1 -1) (1 2))",
+        "& (replicate) count must not be negative, got -1",
+    );
+    assert_err(
+        "(&| (1.5 1))",
+        "& (where) expects bool or int counts, got float",
+    );
+}
+
+#[test]
+fn index_of_and_member() {
+    assert_pop_last("(⍳| (5 6 7 5) (5 7 9))", ints(&[0, 2, 4]));
+    assert_pop_last("(⍳| (1 2) 2)", NN::nd(E::INT(1)));
+    assert_pop_last("(∊| (1 9 2) (1 2 3))", bools(&[true, false, true]));
+    // Ints and floats match when = says they are equal
+    assert_pop_last("(∊| 1 (1.0 2.0))", NN::nd(E::BOOL(true)));
+    assert_pop_last("(⍳| (1.0 2) 1)", NN::nd(E::INT(0)));
+    assert_pop_last("(∊| 1.5 (1 2))", NN::nd(E::BOOL(false)));
+    assert_pop_last("(∊| (=| (1 2) (1 3)) (1 5))", bools(&[true, false]));
+}
+
+#[test]
+fn nan_keys() {
+    // ∞ - ∞: NaN is found, and groups, like any other value
+    let nan = "(-/| (ρ| 2 (×/| (ρ| 20 100000000000000000000.0))))";
+    assert_pop_last(&format!("(⍳| (ρ| 2 {}) {})", nan, nan), NN::nd(E::INT(0)));
+    assert_pop_last(
+        &format!("(∊| {} (ρ| 2 {}))", nan, nan),
+        NN::nd(E::BOOL(true)),
+    );
+    assert_pop_last(&format!("(⊔| (ρ| 3 {}))", nan), rows(&[&[0, 1, 2]]));
+}
+
+#[test]
+fn unique_and_set_ops() {
+    assert_pop_last("(∪| (3 1 3 2 1))", ints(&[3, 1, 2]));
+    assert_pop_last("(∪| (1 2 2) (2 3 3))", ints(&[1, 2, 2, 3]));
+    assert_pop_last("(∩| (1 2 3 4) (4 2))", ints(&[2, 4]));
+    assert_pop_last("(~| (1 2 3 4) (4 2))", ints(&[1, 3]));
+    assert_pop_last("(~| (1 2) (1 2))", ints(&[]));
+}
+
+#[test]
+fn group_and_key() {
+    assert_pop_last("(⊔| (1 2 1 3 2))", rows(&[&[0, 2], &[1, 4], &[3]]));
+    assert_pop_last("(⊔| (1 2 1) (10 20 30))", rows(&[&[10, 30], &[20]]));
+    // Sum by key: results line up with (∪| keys)
+    assert_pop_last(
+        "(↻ (: sum (λ (x) (+/| x))) (sum⌸| (1 2 1 3) (10 20 30 40)))",
+        ints(&[40, 20, 40]),
+    );
+    assert_pop_last(
+        "(↻ (: first (λ (x) (@| x 0))) (first⌸| (7 8 7)))",
+        ints(&[0, 1]),
+    );
+    assert_pop_last("(-⌸| (1 2 1) (10 20 30))", rows(&[&[-10, -30], &[-20]]));
+    // A derived function works as the operand: +/⌸ sums each group
+    assert_pop_last("(+/⌸| (1 2 1) (4 5 6))", ints(&[10, 5]));
+    assert_pop_last("(+/⌸| (1 2 1))", ints(&[2, 1]));
+    assert_pop_last(
+        "(↻ (: f (λ (a b) (+| a b))) (f/⌸| (1 2 1) (4 5 6)))",
+        ints(&[10, 5]),
+    );
+}
+
+#[test]
+fn chained_combinators() {
+    assert_pop_last("(+/ǁ| ((1 2) (3 4)))", ints(&[3, 7]));
+    // Converge is a loop in the bytecode, so it must come last
+    assert!(I::fstring("(+⍟⌸| (1 1) (2 3))").is_err());
+}
+
+#[test]
+fn group_errors() {
+    assert_err(
+        "(⊔| (1 2) (1 2 3))",
+        "⊔: length mismatch: 2 keys for 3 values",
+    );
+    assert_err(
+        "(-⌸| (1 2) (1 2 3))",
+        "⌸: length mismatch: 2 keys for 3 values",
+    );
+}